@@ -28,4 +28,47 @@ pub const GROUP_KEY: &str = "group";
 pub const INTERFACE_KEY: &str = "interface";
 pub const ANYHOST_KEY: &str = "anyhost";
 pub const SIDE_KEY: &str = "side";
+pub const WEIGHT_KEY: &str = "weight";
+pub const WARMUP_KEY: &str = "warmup";
 pub const TIMESTAMP_KEY: &str = "timestamp";
+
+// tag routing, providers register `dubbo.tag`, consumers send both keys as request metadata
+pub const TAG_KEY: &str = "dubbo.tag";
+pub const FORCE_TAG_KEY: &str = "dubbo.force.tag";
+
+// zone aware routing, the locality of providers and consumers
+pub const ZONE_KEY: &str = "zone";
+pub const REGION_KEY: &str = "region";
+pub const ZONE_MIN_HEALTHY_KEY: &str = "zone.min.healthy";
+
+// cluster URL key, method level values are keyed as `{method}.{key}`
+pub const RETRIES_KEY: &str = "retries";
+pub const LOADBALANCE_KEY: &str = "loadbalance";
+pub const CLUSTER_KEY: &str = "cluster";
+pub const FAILBACK_TASKS_KEY: &str = "failbacktasks";
+pub const FORKS_KEY: &str = "forks";
+pub const TIMEOUT_KEY: &str = "timeout";
+pub const HASH_KEY: &str = "hash.key";
+pub const HASH_NODES_KEY: &str = "hash.nodes";
+pub const HASH_FIELD_KEY: &str = "hash.field";
+pub const BROADCAST_FAIL_PERCENT_KEY: &str = "broadcast.fail.percent";
+pub const MOCK_KEY: &str = "mock";
+pub const CONNECTIONS_KEY: &str = "connections";
+pub const MAX_CONCURRENT_STREAMS_KEY: &str = "max.concurrent.streams";
+
+// outlier detection URL key, durations are in milliseconds
+pub const OUTLIER_DETECTION_KEY: &str = "outlier.detection";
+pub const OUTLIER_CONSECUTIVE_FAILURES_KEY: &str = "outlier.consecutive.failures";
+pub const OUTLIER_FAILURE_PERCENT_KEY: &str = "outlier.failure.percent";
+pub const OUTLIER_MINIMUM_REQUESTS_KEY: &str = "outlier.minimum.requests";
+pub const OUTLIER_INTERVAL_KEY: &str = "outlier.interval";
+pub const OUTLIER_BASE_EJECTION_TIME_KEY: &str = "outlier.base.ejection.time";
+pub const OUTLIER_MAX_EJECTION_TIME_KEY: &str = "outlier.max.ejection.time";
+pub const OUTLIER_MAX_EJECTION_PERCENT_KEY: &str = "outlier.max.ejection.percent";
+
+// active health check URL key, durations are in milliseconds
+pub const HEALTH_CHECK_KEY: &str = "health.check";
+pub const HEALTH_CHECK_INTERVAL_KEY: &str = "health.check.interval";
+pub const HEALTH_CHECK_TIMEOUT_KEY: &str = "health.check.timeout";
+pub const HEALTH_CHECK_HEALTHY_THRESHOLD_KEY: &str = "health.check.healthy.threshold";
+pub const HEALTH_CHECK_UNHEALTHY_THRESHOLD_KEY: &str = "health.check.unhealthy.threshold";
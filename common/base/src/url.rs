@@ -60,10 +60,9 @@ impl Url {
             location: uri.authority()?.to_string(),
             service_key: uri.path().trim_start_matches('/').to_string(),
             service_name: uri.path().trim_start_matches('/').to_string(),
-            params: if let Some(..) = query {
-                Url::decode(query.unwrap())
-            } else {
-                HashMap::new()
+            params: match query {
+                Some(query) => Url::decode(query),
+                None => HashMap::new(),
             },
         };
         url_inst.renew_raw_url_string();
@@ -82,6 +81,12 @@ impl Url {
         self.params.get(key).cloned()
     }
 
+    // method level param `{method}.{key}` takes precedence over `{key}`
+    pub fn get_method_param(&self, method: &str, key: &str) -> Option<String> {
+        self.get_param(&format!("{}.{}", method, key))
+            .or_else(|| self.get_param(key))
+    }
+
     fn encode_param(&self) -> String {
         let mut params_vec: Vec<String> = Vec::new();
         for (k, v) in self.params.iter() {
@@ -176,9 +181,9 @@ impl Display for Url {
     }
 }
 
-impl Into<Uri> for Url {
-    fn into(self) -> Uri {
-        self.raw_url_string.parse::<Uri>().unwrap()
+impl From<Url> for Uri {
+    fn from(url: Url) -> Self {
+        url.raw_url_string.parse::<Uri>().unwrap()
     }
 }
 
@@ -241,6 +246,16 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_get_method_param() {
+        let url =
+            Url::from_url("tri://127.0.0.1:20000/helloworld.Greeter?retries=2&SayHello.retries=5")
+                .unwrap();
+        assert_eq!(url.get_method_param("SayHello", "retries").unwrap(), "5");
+        assert_eq!(url.get_method_param("SayBye", "retries").unwrap(), "2");
+        assert!(url.get_method_param("SayHello", "loadbalance").is_none());
+    }
+
     #[test]
     fn test2() {
         let url: Url = "tri://0.0.0.0:8888/org.apache.dubbo.sample.tri.Greeter".into();
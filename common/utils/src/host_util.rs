@@ -20,10 +20,9 @@ use port_selector::is_free;
 
 pub use port_selector::Port;
 
-// get local ip for linux/macos/windows
-#[allow(dead_code)]
-pub(crate) fn local_ip() -> IpAddr {
-    local_ip_address::local_ip().unwrap()
+// get local ip for linux/macos/windows, none if there is no network interface up
+pub fn local_ip() -> Option<IpAddr> {
+    local_ip_address::local_ip().ok()
 }
 
 #[allow(dead_code)]
@@ -53,7 +52,7 @@ mod tests {
     #[test]
     fn test_local_ip() {
         let ip = local_ip();
-        println!("ip: {}", ip);
+        println!("ip: {:?}", ip);
     }
 
     #[test]
@@ -23,7 +23,10 @@ use dubbo_utils::yaml_util::yaml_file_parser;
 use once_cell::sync::OnceCell;
 use serde::{Deserialize, Serialize};
 
-use super::{protocol::ProtocolConfig, provider::ProviderConfig, service::ServiceConfig};
+use super::{
+    consumer::ConsumerConfig, protocol::ProtocolConfig, provider::ProviderConfig,
+    service::ServiceConfig,
+};
 
 pub const DUBBO_CONFIG_PATH: &str = "application.yaml";
 
@@ -41,6 +44,9 @@ pub struct RootConfig {
     #[serde(default)]
     pub provider: ProviderConfig,
 
+    #[serde(default)]
+    pub consumer: ConsumerConfig,
+
     #[serde(default)]
     pub registries: HashMap<String, RegistryConfig>,
 
@@ -63,6 +69,7 @@ impl RootConfig {
             protocols: HashMap::new(),
             registries: HashMap::new(),
             provider: ProviderConfig::new(),
+            consumer: ConsumerConfig::new(),
             data: HashMap::new(),
         }
     }
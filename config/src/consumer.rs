@@ -0,0 +1,58 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::reference::ReferenceConfig;
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct ConsumerConfig {
+    #[serde(default)]
+    pub references: HashMap<String, ReferenceConfig>,
+    // locality of the consumer, the `zone` and `region` params of a reference override them
+    #[serde(default)]
+    pub zone: String,
+    #[serde(default)]
+    pub region: String,
+}
+
+impl ConsumerConfig {
+    pub fn new() -> Self {
+        ConsumerConfig {
+            references: HashMap::new(),
+            zone: String::new(),
+            region: String::new(),
+        }
+    }
+
+    pub fn with_references(mut self, references: HashMap<String, ReferenceConfig>) -> Self {
+        self.references = references;
+        self
+    }
+
+    pub fn with_zone(mut self, zone: String) -> Self {
+        self.zone = zone;
+        self
+    }
+
+    pub fn with_region(mut self, region: String) -> Self {
+        self.region = region;
+        self
+    }
+}
@@ -18,7 +18,12 @@
 pub use config::*;
 
 pub mod config;
+pub mod consumer;
+pub mod method;
 pub mod protocol;
 pub mod provider;
+pub mod reference;
 pub mod registry;
+pub mod router;
 pub mod service;
+pub mod tls;
@@ -0,0 +1,51 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Method level config of a reference, overrides the reference level values for the method.
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct MethodConfig {
+    // random, roundrobin, leastactive, consistenthash, shortestresponse or p2c
+    #[serde(default)]
+    pub loadbalance: String,
+    // eg. `force:return null`, `fail:return <value>` or `fail` to call the fallback of the client
+    #[serde(default)]
+    pub mock: String,
+    // extra method url params, eg. retries
+    #[serde(default)]
+    pub params: HashMap<String, String>,
+}
+
+impl MethodConfig {
+    pub fn loadbalance(self, loadbalance: String) -> Self {
+        Self {
+            loadbalance,
+            ..self
+        }
+    }
+
+    pub fn mock(self, mock: String) -> Self {
+        Self { mock, ..self }
+    }
+
+    pub fn params(self, params: HashMap<String, String>) -> Self {
+        Self { params, ..self }
+    }
+}
@@ -19,6 +19,8 @@ use std::collections::HashMap;
 
 use serde::{Deserialize, Serialize};
 
+use crate::tls::TlsConfig;
+
 pub const DEFAULT_PROTOCOL: &str = "triple";
 
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
@@ -26,6 +28,9 @@ pub struct Protocol {
     pub ip: String,
     pub port: String,
     pub name: String,
+    // the clients of the protocol connect by tls, unless their reference has its own
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
 
     #[serde(skip_serializing, skip_deserializing)]
     pub params: HashMap<String, String>,
@@ -51,6 +56,13 @@ impl Protocol {
         Self { port, ..self }
     }
 
+    pub fn tls(self, tls: TlsConfig) -> Self {
+        Self {
+            tls: Some(tls),
+            ..self
+        }
+    }
+
     pub fn params(self, params: HashMap<String, String>) -> Self {
         Self { params, ..self }
     }
@@ -62,25 +74,11 @@ impl Protocol {
 
 impl ProtocolRetrieve for ProtocolConfig {
     fn get_protocol(&self, protocol_key: &str) -> Option<Protocol> {
-        let result = self.get(protocol_key);
-        if let Some(..) = result {
-            Some(result.unwrap().clone())
-        } else {
-            None
-        }
+        self.get(protocol_key).cloned()
     }
 
     fn get_protocol_or_default(&self, protocol_key: &str) -> Protocol {
-        let result = self.get_protocol(protocol_key);
-        if let Some(..) = result {
-            result.unwrap()
-        } else {
-            let result = self.get_protocol(protocol_key);
-            if let Some(..) = result {
-                panic!("default triple base dose not defined.")
-            } else {
-                result.unwrap()
-            }
-        }
+        self.get_protocol(protocol_key)
+            .expect("default triple base dose not defined.")
     }
 }
@@ -29,6 +29,11 @@ pub struct ProviderConfig {
     pub protocol_ids: Vec<String>,
     #[serde(default)]
     pub services: HashMap<String, ServiceConfig>,
+    // locality of the provider, consumers prefer the providers of their zone
+    #[serde(default)]
+    pub zone: String,
+    #[serde(default)]
+    pub region: String,
 }
 
 impl ProviderConfig {
@@ -37,6 +42,8 @@ impl ProviderConfig {
             registry_ids: vec![],
             protocol_ids: vec![],
             services: HashMap::new(),
+            zone: String::new(),
+            region: String::new(),
         }
     }
 
@@ -54,4 +61,14 @@ impl ProviderConfig {
         self.services = services;
         self
     }
+
+    pub fn with_zone(mut self, zone: String) -> Self {
+        self.zone = zone;
+        self
+    }
+
+    pub fn with_region(mut self, region: String) -> Self {
+        self.region = region;
+        self
+    }
 }
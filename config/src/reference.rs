@@ -0,0 +1,114 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{method::MethodConfig, router::RouterConfig, tls::TlsConfig};
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct ReferenceConfig {
+    // direct connect to the providers, eg. tri://127.0.0.1:8888, endpoints are separated by `,`
+    // and the ones named by host, eg. a headless kubernetes service, are resolved periodically
+    #[serde(default)]
+    pub url: String,
+    #[serde(default)]
+    pub protocol: String,
+    #[serde(default)]
+    pub interface: String,
+    // failover, failfast, failsafe, failback, forking or broadcast
+    #[serde(default)]
+    pub cluster: String,
+    // random, roundrobin, leastactive, consistenthash, shortestresponse or p2c
+    #[serde(default)]
+    pub loadbalance: String,
+    // degrades the calls, eg. `force:return null`, `fail:return <value>` or `fail` to call the
+    // fallback of the client
+    #[serde(default)]
+    pub mock: String,
+    // connections to each provider, the calls round-robin over them, 0 shares one connection
+    // with the other references
+    #[serde(default)]
+    pub connections: usize,
+    // connects to the providers by tls, overrides the tls of the protocol
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
+    // extra reference url params, eg. retries
+    #[serde(default)]
+    pub params: HashMap<String, String>,
+    // keyed by method name
+    #[serde(default)]
+    pub methods: HashMap<String, MethodConfig>,
+    #[serde(default)]
+    pub router: RouterConfig,
+}
+
+impl ReferenceConfig {
+    pub fn url(self, url: String) -> Self {
+        Self { url, ..self }
+    }
+
+    pub fn protocol(self, protocol: String) -> Self {
+        Self { protocol, ..self }
+    }
+
+    pub fn interface(self, interface: String) -> Self {
+        Self { interface, ..self }
+    }
+
+    pub fn cluster(self, cluster: String) -> Self {
+        Self { cluster, ..self }
+    }
+
+    pub fn loadbalance(self, loadbalance: String) -> Self {
+        Self {
+            loadbalance,
+            ..self
+        }
+    }
+
+    pub fn mock(self, mock: String) -> Self {
+        Self { mock, ..self }
+    }
+
+    pub fn connections(self, connections: usize) -> Self {
+        Self {
+            connections,
+            ..self
+        }
+    }
+
+    pub fn tls(self, tls: TlsConfig) -> Self {
+        Self {
+            tls: Some(tls),
+            ..self
+        }
+    }
+
+    pub fn params(self, params: HashMap<String, String>) -> Self {
+        Self { params, ..self }
+    }
+
+    pub fn methods(self, methods: HashMap<String, MethodConfig>) -> Self {
+        Self { methods, ..self }
+    }
+
+    pub fn router(self, router: RouterConfig) -> Self {
+        Self { router, ..self }
+    }
+}
@@ -22,4 +22,13 @@ pub struct RegistryConfig {
     pub protocol: String,
     #[serde(default)]
     pub address: String,
+    // locality of the providers of the registry, unless they register their own
+    #[serde(default)]
+    pub zone: String,
+    #[serde(default)]
+    pub region: String,
+    // consumers use the providers of a preferred registry, and fall back to the other
+    // registries when it has no provider
+    #[serde(default)]
+    pub preferred: bool,
 }
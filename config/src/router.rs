@@ -0,0 +1,114 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::{collections::HashMap, fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+/// Routing rules of a reference.
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct RouterConfig {
+    // condition rules, eg. `method = get* => host = 10.0.1.*`
+    #[serde(default)]
+    pub conditions: Vec<String>,
+    // return no provider instead of ignoring a condition rule no provider matches
+    #[serde(default)]
+    pub force: bool,
+    // traffic split between provider subsets
+    #[serde(default)]
+    pub traffic: Option<TrafficConfig>,
+    // yaml file of a `TrafficConfig`, reloaded when it changes, overrides `traffic`
+    #[serde(default)]
+    pub traffic_file: String,
+}
+
+impl RouterConfig {
+    pub fn conditions(self, conditions: Vec<String>) -> Self {
+        Self { conditions, ..self }
+    }
+
+    pub fn force(self, force: bool) -> Self {
+        Self { force, ..self }
+    }
+
+    pub fn traffic(self, traffic: TrafficConfig) -> Self {
+        Self {
+            traffic: Some(traffic),
+            ..self
+        }
+    }
+
+    pub fn traffic_file(self, traffic_file: String) -> Self {
+        Self {
+            traffic_file,
+            ..self
+        }
+    }
+}
+
+/// Traffic split rule, eg. 95% of the calls to the providers of version 1.0 and 5% to 2.0,
+/// and the calls of the beta users to 2.0 only:
+///
+/// ```yaml
+/// subsets:
+///   v1:
+///     version: "1.0"
+///   v2:
+///     version: "2.0"
+/// routes:
+///   - headers:
+///       user-type: beta
+///     split:
+///       - subset: v2
+///         weight: 100
+///   - split:
+///       - subset: v1
+///         weight: 95
+///       - subset: v2
+///         weight: 5
+/// ```
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct TrafficConfig {
+    // subset name -> the url params of its providers
+    #[serde(default)]
+    pub subsets: HashMap<String, HashMap<String, String>>,
+    // the first route matching the request metadata splits it
+    #[serde(default)]
+    pub routes: Vec<TrafficRoute>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct TrafficRoute {
+    // request metadata the route matches, matches every request when empty
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    #[serde(default)]
+    pub split: Vec<SubsetWeight>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct SubsetWeight {
+    pub subset: String,
+    pub weight: u32,
+}
+
+impl TrafficConfig {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let data = fs::read(path)?;
+        serde_yaml::from_slice(&data).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
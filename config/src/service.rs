@@ -23,6 +23,8 @@ pub struct ServiceConfig {
     pub group: String,
     pub protocol: String,
     pub interface: String,
+    #[serde(default)]
+    pub tag: String,
 }
 
 impl ServiceConfig {
@@ -42,6 +44,10 @@ impl ServiceConfig {
         Self { protocol, ..self }
     }
 
+    pub fn tag(self, tag: String) -> Self {
+        Self { tag, ..self }
+    }
+
     // pub fn get_url(&self) -> Vec<Url> {
     //     let mut urls = Vec::new();
     //     for (_, conf) in self.protocol_configs.iter() {
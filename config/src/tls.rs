@@ -0,0 +1,63 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use serde::{Deserialize, Serialize};
+
+/// TLS of a protocol or reference, the files are in PEM format.
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct TlsConfig {
+    // trusted ca certs, the certs of the peer are verified against them
+    #[serde(default)]
+    pub ca: String,
+    // cert chain and private key presented to the peer, a client presents them for mutual tls
+    #[serde(default)]
+    pub cert: String,
+    #[serde(default)]
+    pub key: String,
+    // name verified against the server cert instead of the provider host, required to
+    // connect to providers registered by ip
+    #[serde(default)]
+    pub server_name: String,
+    // reload the files when they change, eg. certs rotated by a secret mount
+    #[serde(default)]
+    pub watch: bool,
+}
+
+impl TlsConfig {
+    pub fn ca(self, ca: String) -> Self {
+        Self { ca, ..self }
+    }
+
+    pub fn cert(self, cert: String) -> Self {
+        Self { cert, ..self }
+    }
+
+    pub fn key(self, key: String) -> Self {
+        Self { key, ..self }
+    }
+
+    pub fn server_name(self, server_name: String) -> Self {
+        Self {
+            server_name,
+            ..self
+        }
+    }
+
+    pub fn watch(self, watch: bool) -> Self {
+        Self { watch, ..self }
+    }
+}
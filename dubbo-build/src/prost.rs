@@ -332,7 +332,7 @@ impl super::Method for DubboMethod {
         let convert_type = |proto_type: &str, rust_type: &str| -> TokenStream {
             if (is_google_type(proto_type) && !compile_well_known_types)
                 || rust_type.starts_with("::")
-                || NON_PATH_TYPE_ALLOWLIST.iter().any(|t| *t == rust_type)
+                || NON_PATH_TYPE_ALLOWLIST.contains(&rust_type)
             {
                 rust_type.parse::<TokenStream>().unwrap()
             } else if rust_type.starts_with("crate::") {
@@ -172,7 +172,7 @@ fn generate_trait<T: Service>(
     server_trait: Ident,
 ) -> TokenStream {
     let methods = generate_trait_methods(service, proto_path, compile_well_known_types);
-    let trait_doc = generate_doc_comment(&format!(
+    let trait_doc = generate_doc_comment(format!(
         "Generated trait containing gRPC methods that should be implemented for use with {}Server.",
         service.name()
     ));
@@ -218,7 +218,7 @@ fn generate_trait_methods<T: Service>(
             }
             (false, true) => {
                 let stream = quote::format_ident!("{}Stream", method.identifier());
-                let stream_doc = generate_doc_comment(&format!(
+                let stream_doc = generate_doc_comment(format!(
                     "Server streaming response type for the {} method.",
                     method.identifier()
                 ));
@@ -234,7 +234,7 @@ fn generate_trait_methods<T: Service>(
             }
             (true, true) => {
                 let stream = quote::format_ident!("{}Stream", method.identifier());
-                let stream_doc = generate_doc_comment(&format!(
+                let stream_doc = generate_doc_comment(format!(
                     "Server streaming response type for the {} method.",
                     method.identifier()
                 ));
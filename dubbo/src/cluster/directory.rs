@@ -16,23 +16,40 @@
  */
 
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fmt::Debug,
+    net::{IpAddr, SocketAddr},
     str::FromStr,
-    sync::{Arc, RwLock},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, RwLock, Weak,
+    },
+    time::Duration,
 };
 
 use crate::{
+    cluster::router::local_host,
     invocation::{Invocation, RpcInvocation},
-    registry::{memory_registry::MemoryNotifyListener, BoxRegistry, RegistryWrapper},
+    registry::{
+        create_registry,
+        memory_registry::{MemoryNotifyListener, ServiceInstances},
+        BoxRegistry,
+    },
+    triple::transport::resolver::dns::DnsResolver,
 };
-use dubbo_base::Url;
+use arc_swap::ArcSwap;
+use dubbo_base::{
+    constants::{LOCALHOST_IP, REGION_KEY, ZONE_KEY},
+    Url,
+};
+use dubbo_config::registry::RegistryConfig;
 use dubbo_logger::tracing;
+use tower_service::Service;
 
 /// Directory.
 ///
 /// [Directory Service](http://en.wikipedia.org/wiki/Directory_service)
-pub trait Directory: Debug + DirectoryClone {
+pub trait Directory: Debug + DirectoryClone + Send + Sync {
     fn list(&self, invocation: Arc<RpcInvocation>) -> Vec<Url>;
 }
 
@@ -55,100 +72,487 @@ impl Clone for Box<dyn Directory> {
     }
 }
 
-#[derive(Debug)]
+/// How often the names of a [`StaticDirectory`] are resolved again.
+pub const DEFAULT_RESOLVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Directory of a fixed list of endpoints, eg. `http://10.0.0.1:8888` or `127.0.0.1:8888`.
+///
+/// The endpoints named by host, eg. a headless Kubernetes service, are resolved in the
+/// background from the first call, then every resolve interval. A name is listed as is until
+/// it is resolved, and keeps its last addresses when resolving fails.
+#[derive(Debug, Clone)]
 pub struct StaticDirectory {
-    uri: http::Uri,
+    endpoints: Arc<Vec<Endpoint>>,
+    // addresses of the endpoints named by host, keyed by `host:port`
+    resolved: Arc<RwLock<HashMap<String, Vec<SocketAddr>>>>,
+    interval: Duration,
+    resolving: Arc<AtomicBool>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Endpoint {
+    host: String,
+    port: u16,
+}
+
+impl Endpoint {
+    fn parse(endpoint: &str) -> Result<Self, crate::StdError> {
+        let endpoint = endpoint.trim();
+        let uri = if endpoint.contains("://") {
+            http::Uri::from_str(endpoint)
+        } else {
+            http::Uri::from_str(&format!("http://{}", endpoint))
+        }
+        .map_err(|err| format!("invalid endpoint {}: {}", endpoint, err))?;
+        let host = uri
+            .host()
+            .ok_or_else(|| format!("invalid endpoint {}: missing host", endpoint))?;
+        let port = uri
+            .port_u16()
+            .ok_or_else(|| format!("invalid endpoint {}: missing port", endpoint))?;
+        Ok(Endpoint {
+            host: host
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .to_string(),
+            port,
+        })
+    }
+
+    fn is_name(&self) -> bool {
+        self.host.parse::<IpAddr>().is_err()
+    }
+
+    fn key(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
 }
 
 impl StaticDirectory {
+    /// Endpoints separated by `,`, the invalid ones are logged and left out.
     pub fn new(host: &str) -> StaticDirectory {
-        let uri = match http::Uri::from_str(host) {
-            Ok(v) => v,
-            Err(err) => {
-                tracing::error!("http uri parse error: {}, host: {}", err, host);
-                panic!("http uri parse error: {}, host: {}", err, host)
-            }
-        };
-        StaticDirectory { uri: uri }
+        let endpoints = host
+            .split(',')
+            .filter(|endpoint| !endpoint.trim().is_empty())
+            .filter_map(|endpoint| match Endpoint::parse(endpoint) {
+                Ok(v) => Some(v),
+                Err(err) => {
+                    tracing::error!("{}", err);
+                    None
+                }
+            })
+            .collect();
+        StaticDirectory::with_endpoints(endpoints)
     }
 
     pub fn from_uri(uri: &http::Uri) -> StaticDirectory {
-        StaticDirectory { uri: uri.clone() }
+        StaticDirectory::new(&uri.to_string())
+    }
+
+    /// Fails on the first invalid endpoint.
+    pub fn from_endpoints<I, S>(endpoints: I) -> Result<StaticDirectory, crate::StdError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let endpoints = endpoints
+            .into_iter()
+            .map(|endpoint| Endpoint::parse(endpoint.as_ref()))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(StaticDirectory::with_endpoints(endpoints))
+    }
+
+    fn with_endpoints(endpoints: Vec<Endpoint>) -> StaticDirectory {
+        StaticDirectory {
+            endpoints: Arc::new(endpoints),
+            resolved: Arc::new(RwLock::new(HashMap::new())),
+            interval: DEFAULT_RESOLVE_INTERVAL,
+            resolving: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn with_resolve_interval(self, interval: Duration) -> Self {
+        Self { interval, ..self }
+    }
+
+    /// Starts resolving the names in the background, once and only in a tokio runtime.
+    fn resolve(&self) {
+        if self.resolving.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        let names: Vec<Endpoint> = self
+            .endpoints
+            .iter()
+            .filter(|endpoint| endpoint.is_name())
+            .cloned()
+            .collect();
+        if names.is_empty() {
+            return;
+        }
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => {
+                runtime.spawn(resolve_loop(
+                    names,
+                    Arc::downgrade(&self.resolved),
+                    self.interval,
+                ));
+            }
+            Err(_) => {
+                tracing::warn!("resolving names needs a tokio runtime, names are listed as is");
+            }
+        }
     }
 }
 
-impl Directory for StaticDirectory {
-    fn list(&self, invocation: Arc<RpcInvocation>) -> Vec<Url> {
-        let url = Url::from_url(&format!(
-            "tri://{}:{}/{}",
-            self.uri.host().unwrap(),
-            self.uri.port().unwrap(),
-            invocation.get_target_service_unique_name(),
-        ))
-        .unwrap();
-        vec![url]
+async fn resolve_loop(
+    names: Vec<Endpoint>,
+    resolved: Weak<RwLock<HashMap<String, Vec<SocketAddr>>>>,
+    interval: Duration,
+) {
+    let mut resolver = DnsResolver::default();
+    loop {
+        for name in names.iter() {
+            let addrs = match resolver.call(name.host.clone()).await {
+                Ok(addrs) => addrs,
+                Err(err) => {
+                    tracing::warn!("fail to resolve {}: {}", name.host, err);
+                    continue;
+                }
+            };
+            let mut addrs: Vec<SocketAddr> = addrs
+                .map(|addr| SocketAddr::new(addr.ip(), name.port))
+                .collect();
+            addrs.sort();
+            addrs.dedup();
+            if addrs.is_empty() {
+                tracing::warn!("no address of {}, keep the last ones", name.host);
+                continue;
+            }
+            let map = match resolved.upgrade() {
+                Some(v) => v,
+                None => return,
+            };
+            let mut map = map.write().unwrap();
+            if map.get(&name.key()) != Some(&addrs) {
+                tracing::info!("addresses of {} changed: {:?}", name.key(), addrs);
+                map.insert(name.key(), addrs);
+            }
+        }
+        if resolved.strong_count() == 0 {
+            return;
+        }
+        tokio::time::sleep(interval).await;
     }
 }
 
-impl DirectoryClone for StaticDirectory {
-    fn clone_box(&self) -> Box<dyn Directory> {
-        Box::new(StaticDirectory {
-            uri: self.uri.clone(),
-        })
+impl Directory for StaticDirectory {
+    fn list(&self, invocation: Arc<RpcInvocation>) -> Vec<Url> {
+        self.resolve();
+        let service_name = invocation.get_target_service_unique_name();
+        let resolved = self.resolved.read().unwrap();
+        let mut addresses = Vec::with_capacity(self.endpoints.len());
+        for endpoint in self.endpoints.iter() {
+            match resolved.get(&endpoint.key()) {
+                Some(addrs) => addresses.extend(addrs.iter().map(|addr| addr.to_string())),
+                None if endpoint.host.contains(':') => {
+                    addresses.push(format!("[{}]:{}", endpoint.host, endpoint.port))
+                }
+                None => addresses.push(endpoint.key()),
+            }
+        }
+        addresses
+            .iter()
+            .filter_map(|address| Url::from_url(&format!("tri://{}/{}", address, service_name)))
+            .collect()
     }
 }
 
-#[derive(Debug)]
+/// Directory of the providers in a registry. A service is subscribed on its first call, then
+/// its providers are kept up to date by the registry events, so listing is a lock-free read.
+#[derive(Debug, Clone)]
 pub struct RegistryDirectory {
-    registry: RegistryWrapper,
-    service_instances: Arc<RwLock<HashMap<String, Vec<Url>>>>,
+    registry: Arc<BoxRegistry>,
+    service_instances: ServiceInstances,
+    // serializes the updates of `service_instances`
+    updating: Arc<Mutex<()>>,
+    subscribed: Arc<Mutex<HashSet<String>>>,
+    // locality given to the providers which do not register one
+    zone: String,
+    region: String,
 }
 
 impl RegistryDirectory {
     pub fn new(registry: BoxRegistry) -> RegistryDirectory {
         RegistryDirectory {
-            registry: RegistryWrapper {
-                registry: Some(registry),
-            },
-            service_instances: Arc::new(RwLock::new(HashMap::new())),
+            registry: Arc::new(registry),
+            service_instances: Arc::new(ArcSwap::from_pointee(HashMap::new())),
+            updating: Arc::new(Mutex::new(())),
+            subscribed: Arc::new(Mutex::new(HashSet::new())),
+            zone: String::new(),
+            region: String::new(),
         }
     }
-}
 
-impl DirectoryClone for RegistryDirectory {
-    fn clone_box(&self) -> Box<dyn Directory> {
-        todo!()
+    /// The zone of the providers of the registry, for the zone aware routing across registries.
+    pub fn with_zone(self, zone: String) -> Self {
+        Self { zone, ..self }
+    }
+
+    pub fn with_region(self, region: String) -> Self {
+        Self { region, ..self }
+    }
+
+    fn with_locality(&self, mut url: Url) -> Url {
+        if !self.zone.is_empty() && url.get_param(ZONE_KEY).is_none() {
+            url.set_param(ZONE_KEY, &self.zone);
+        }
+        if !self.region.is_empty() && url.get_param(REGION_KEY).is_none() {
+            url.set_param(REGION_KEY, &self.region);
+        }
+        url
+    }
+
+    /// Subscribes `service_name` unless it is subscribed, a failed subscription is retried
+    /// on the next call.
+    fn subscribe(&self, service_name: &str) {
+        let mut subscribed = self.subscribed.lock().unwrap();
+        if subscribed.contains(service_name) {
+            return;
+        }
+        let mut url = Url::new();
+        url.scheme = "consumer".to_string();
+        url.ip = local_host().unwrap_or(LOCALHOST_IP).to_string();
+        url.service_name = service_name.to_string();
+        url.service_key = service_name.to_string();
+        let listener = Arc::new(MemoryNotifyListener {
+            service_instances: self.service_instances.clone(),
+            updating: self.updating.clone(),
+        });
+        match self.registry.subscribe(url, listener) {
+            Ok(_) => {
+                subscribed.insert(service_name.to_string());
+            }
+            Err(err) => tracing::error!("fail to subscribe {}: {}", service_name, err),
+        }
+    }
+
+    fn providers(&self, service_name: &str) -> Option<Vec<Url>> {
+        let providers = self.service_instances.load().get(service_name).cloned()?;
+        if self.zone.is_empty() && self.region.is_empty() {
+            return Some(providers.to_vec());
+        }
+        Some(
+            providers
+                .iter()
+                .map(|url| self.with_locality(url.clone()))
+                .collect(),
+        )
     }
 }
 
 impl Directory for RegistryDirectory {
     fn list(&self, invocation: Arc<RpcInvocation>) -> Vec<Url> {
         let service_name = invocation.get_target_service_unique_name();
+        if let Some(providers) = self.providers(&service_name) {
+            return providers;
+        }
+        self.subscribe(&service_name);
+        self.providers(&service_name).unwrap_or_default()
+    }
+}
+
+/// Directory of the providers in several registries, eg. during a migration between
+/// registries. The providers of the preferred registries are used when there are any, else
+/// the ones of the other registries. The providers of an address in several registries are
+/// listed once, as found in the first registry.
+#[derive(Debug, Clone, Default)]
+pub struct MultiRegistryDirectory {
+    preferred: Vec<RegistryDirectory>,
+    others: Vec<RegistryDirectory>,
+}
+
+impl MultiRegistryDirectory {
+    pub fn new() -> Self {
+        MultiRegistryDirectory::default()
+    }
+
+    pub fn with_registry(mut self, directory: RegistryDirectory, preferred: bool) -> Self {
+        if preferred {
+            self.preferred.push(directory);
+        } else {
+            self.others.push(directory);
+        }
+        self
+    }
+
+    /// Creates the registries of `registries`, see [`register_registry_factory`].
+    ///
+    /// [`register_registry_factory`]: crate::registry::register_registry_factory
+    pub fn from_config(
+        registries: &HashMap<String, RegistryConfig>,
+    ) -> Result<Self, crate::StdError> {
+        let mut names: Vec<&String> = registries.keys().collect();
+        names.sort();
+        let mut directory = MultiRegistryDirectory::new();
+        for name in names {
+            let config = &registries[name];
+            let registry = create_registry(config)
+                .map_err(|err| format!("invalid registry {}: {}", name, err))?;
+            let registry = RegistryDirectory::new(registry)
+                .with_zone(config.zone.clone())
+                .with_region(config.region.clone());
+            directory = directory.with_registry(registry, config.preferred);
+        }
+        Ok(directory)
+    }
+
+    fn merge(directories: &[RegistryDirectory], invocation: Arc<RpcInvocation>) -> Vec<Url> {
+        let mut addresses = HashSet::new();
+        let mut providers = Vec::new();
+        for directory in directories.iter() {
+            for url in directory.list(invocation.clone()) {
+                if addresses.insert(url.get_ip_port()) {
+                    providers.push(url);
+                }
+            }
+        }
+        providers
+    }
+}
+
+impl Directory for MultiRegistryDirectory {
+    fn list(&self, invocation: Arc<RpcInvocation>) -> Vec<Url> {
+        let providers = Self::merge(&self.preferred, invocation.clone());
+        if !providers.is_empty() {
+            return providers;
+        }
+        Self::merge(&self.others, invocation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::registry::{
+        Registry, RegistryNotifyListener, ServiceEvent, ADD_ACTION, CHANGE_ACTION, REMOVE_ACTION,
+    };
+
+    #[derive(Default)]
+    struct TestRegistry {
+        subscriptions: Arc<AtomicUsize>,
+        listener: Arc<Mutex<Option<RegistryNotifyListener>>>,
+        providers: Vec<&'static str>,
+    }
+
+    impl TestRegistry {
+        fn new(providers: Vec<&'static str>) -> Self {
+            TestRegistry {
+                providers,
+                ..Default::default()
+            }
+        }
+    }
+
+    impl Registry for TestRegistry {
+        fn register(&mut self, _url: Url) -> Result<(), crate::StdError> {
+            Ok(())
+        }
+
+        fn unregister(&mut self, _url: Url) -> Result<(), crate::StdError> {
+            Ok(())
+        }
+
+        fn subscribe(
+            &self,
+            url: Url,
+            listener: RegistryNotifyListener,
+        ) -> Result<(), crate::StdError> {
+            self.subscriptions.fetch_add(1, Ordering::SeqCst);
+            listener.notify(ServiceEvent {
+                key: url.get_service_name(),
+                action: CHANGE_ACTION.to_string(),
+                service: self.providers.iter().map(|ip| provider(ip)).collect(),
+            });
+            *self.listener.lock().unwrap() = Some(listener);
+            Ok(())
+        }
+
+        fn unsubscribe(
+            &self,
+            _url: Url,
+            _listener: RegistryNotifyListener,
+        ) -> Result<(), crate::StdError> {
+            Ok(())
+        }
+    }
+
+    fn provider(ip: &str) -> Url {
+        Url::from_url(&format!("tri://{}:8888/Greeter", ip)).unwrap()
+    }
+
+    fn list(directory: &dyn Directory) -> Vec<String> {
+        let invocation = RpcInvocation::default().with_service_unique_name("Greeter".to_string());
+        directory
+            .list(Arc::new(invocation))
+            .iter()
+            .map(|u| u.ip.clone())
+            .collect()
+    }
+
+    #[test]
+    fn subscribe_once() {
+        let registry = TestRegistry::new(vec!["10.0.0.1"]);
+        let subscriptions = registry.subscriptions.clone();
+        let listener = registry.listener.clone();
+        let directory = RegistryDirectory::new(Box::new(registry));
+        assert_eq!(list(&directory), vec!["10.0.0.1"]);
+        assert_eq!(list(directory.clone_box().as_ref()), vec!["10.0.0.1"]);
+        assert_eq!(subscriptions.load(Ordering::SeqCst), 1);
+
+        let notify = |action: &str, ip: &str| {
+            let listener = listener.lock().unwrap().clone().unwrap();
+            listener.notify(ServiceEvent {
+                key: "Greeter".to_string(),
+                action: action.to_string(),
+                service: vec![provider(ip)],
+            });
+        };
+        notify(ADD_ACTION, "10.0.0.2");
+        assert_eq!(list(&directory), vec!["10.0.0.1", "10.0.0.2"]);
+        notify(REMOVE_ACTION, "10.0.0.1");
+        assert_eq!(list(&directory), vec!["10.0.0.2"]);
+        notify(CHANGE_ACTION, "10.0.0.3");
+        assert_eq!(list(&directory), vec!["10.0.0.3"]);
+    }
+
+    #[test]
+    fn prefer_registry() {
+        let registry = |providers| RegistryDirectory::new(Box::new(TestRegistry::new(providers)));
+        let directory = MultiRegistryDirectory::new()
+            .with_registry(registry(vec!["10.0.0.1", "10.0.0.2"]), false)
+            .with_registry(registry(vec!["10.0.0.2", "10.0.0.3"]), false);
+        assert_eq!(list(&directory), vec!["10.0.0.1", "10.0.0.2", "10.0.0.3"]);
+
+        let preferred = directory
+            .clone()
+            .with_registry(registry(vec!["10.0.0.4"]), true);
+        assert_eq!(list(&preferred), vec!["10.0.0.4"]);
+        // the preferred registry has no provider
+        let preferred = directory.with_registry(registry(vec![]), true);
+        assert_eq!(list(&preferred), vec!["10.0.0.1", "10.0.0.2", "10.0.0.3"]);
+    }
+
+    #[test]
+    fn static_endpoints() {
+        let directory =
+            StaticDirectory::new("http://10.0.0.1:8888, 10.0.0.2:8888,bad host,10.0.0.3");
+        assert_eq!(list(&directory), vec!["10.0.0.1", "10.0.0.2"]);
+        assert!(StaticDirectory::from_endpoints(["10.0.0.1:8888", "10.0.0.3"]).is_err());
 
-        let url = Url::from_url(&format!(
-            "triple://{}:{}/{}",
-            "127.0.0.1", "8888", service_name
-        ))
-        .unwrap();
-
-        self.registry
-            .registry
-            .as_ref()
-            .expect("msg")
-            .subscribe(
-                url,
-                Arc::new(MemoryNotifyListener {
-                    service_instances: Arc::clone(&self.service_instances),
-                }),
-            )
-            .expect("subscribe");
-
-        let map = self
-            .service_instances
-            .read()
-            .expect("service_instances.read");
-        let binding = Vec::new();
-        let url_vec = map.get(&service_name).unwrap_or(&binding);
-        url_vec.to_vec()
+        // listed as is until resolved
+        let directory = StaticDirectory::from_endpoints(["tri://provider.local:8888"]).unwrap();
+        assert_eq!(list(&directory), vec!["provider.local"]);
     }
 }
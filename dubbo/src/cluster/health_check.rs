@@ -0,0 +1,443 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc, RwLock,
+    },
+    time::Duration,
+};
+
+use dubbo_base::{
+    constants::{
+        CLUSTER_KEY, HEALTH_CHECK_HEALTHY_THRESHOLD_KEY, HEALTH_CHECK_INTERVAL_KEY,
+        HEALTH_CHECK_TIMEOUT_KEY, HEALTH_CHECK_UNHEALTHY_THRESHOLD_KEY,
+    },
+    node::Node,
+    Url,
+};
+use dubbo_logger::tracing;
+
+use crate::{
+    cluster::{
+        directory::{Directory, StaticDirectory},
+        support::cluster_invoker::ClusterInvoker,
+    },
+    health::{generated::health_client::HealthClient, HealthCheckRequest, ServingStatus},
+    invocation::{Request, RpcInvocation},
+    status::Code,
+    triple::{client::builder::ClientBuilder, transport::pool::ConnectionPool},
+};
+
+/// Settings of the active health check, read from the reference url.
+#[derive(Debug, Clone)]
+pub struct HealthCheckConfig {
+    pub interval: Duration,
+    pub timeout: Duration,
+    // consecutive passed checks to mark an unavailable provider available
+    pub healthy_threshold: u32,
+    // consecutive failed checks to mark an available provider unavailable
+    pub unhealthy_threshold: u32,
+    // the service whose status is checked, the server overall status if empty
+    pub service: String,
+}
+
+impl Default for HealthCheckConfig {
+    fn default() -> Self {
+        HealthCheckConfig {
+            interval: Duration::from_secs(10),
+            timeout: Duration::from_secs(1),
+            healthy_threshold: 2,
+            unhealthy_threshold: 3,
+            service: String::new(),
+        }
+    }
+}
+
+impl HealthCheckConfig {
+    pub fn from_url(url: &Url) -> Self {
+        fn param<T: FromStr>(url: &Url, key: &str, default: T) -> T {
+            url.get_param(key)
+                .and_then(|v| v.parse::<T>().ok())
+                .unwrap_or(default)
+        }
+        let default = HealthCheckConfig::default();
+        HealthCheckConfig {
+            interval: Duration::from_millis(param(
+                url,
+                HEALTH_CHECK_INTERVAL_KEY,
+                default.interval.as_millis() as u64,
+            )),
+            timeout: Duration::from_millis(param(
+                url,
+                HEALTH_CHECK_TIMEOUT_KEY,
+                default.timeout.as_millis() as u64,
+            )),
+            healthy_threshold: param(
+                url,
+                HEALTH_CHECK_HEALTHY_THRESHOLD_KEY,
+                default.healthy_threshold,
+            )
+            .max(1),
+            unhealthy_threshold: param(
+                url,
+                HEALTH_CHECK_UNHEALTHY_THRESHOLD_KEY,
+                default.unhealthy_threshold,
+            )
+            .max(1),
+            service: url.get_service_name(),
+        }
+    }
+}
+
+/// A provider under active health check, it is available until enough checks fail.
+#[derive(Debug)]
+pub struct ProviderNode {
+    url: Arc<Url>,
+    available: AtomicBool,
+    destroyed: AtomicBool,
+    passes: AtomicU32,
+    failures: AtomicU32,
+}
+
+impl ProviderNode {
+    fn new(url: Url) -> Self {
+        ProviderNode {
+            url: Arc::new(url),
+            available: AtomicBool::new(true),
+            destroyed: AtomicBool::new(false),
+            passes: AtomicU32::new(0),
+            failures: AtomicU32::new(0),
+        }
+    }
+
+    fn report(&self, serving: bool, config: &HealthCheckConfig) {
+        if serving {
+            self.failures.store(0, Ordering::SeqCst);
+            let passes = self.passes.fetch_add(1, Ordering::SeqCst) + 1;
+            if passes >= config.healthy_threshold && !self.available.swap(true, Ordering::SeqCst) {
+                tracing::info!("provider {} is available again", self.url);
+            }
+        } else {
+            self.passes.store(0, Ordering::SeqCst);
+            let failures = self.failures.fetch_add(1, Ordering::SeqCst) + 1;
+            if failures >= config.unhealthy_threshold
+                && self.available.swap(false, Ordering::SeqCst)
+            {
+                tracing::warn!(
+                    "provider {} failed health check, mark unavailable",
+                    self.url
+                );
+            }
+        }
+    }
+}
+
+impl Node for ProviderNode {
+    fn get_url(&self) -> Arc<Url> {
+        self.url.clone()
+    }
+
+    fn is_available(&self) -> bool {
+        self.available.load(Ordering::SeqCst)
+    }
+
+    fn destroy(&self) {
+        self.destroyed.store(true, Ordering::SeqCst);
+    }
+
+    fn is_destroyed(&self) -> bool {
+        self.destroyed.load(Ordering::SeqCst)
+    }
+}
+
+/// Checks the listed providers periodically by `grpc.health.v1.Health/Check`.
+///
+/// A provider is checked from the first time it is listed until it leaves the list. A provider
+/// which does not implement the health service is considered serving.
+#[derive(Debug)]
+pub struct HealthChecker {
+    config: HealthCheckConfig,
+    // the connections of the reference, the checks use its TLS too
+    pool: Option<ConnectionPool>,
+    nodes: RwLock<HashMap<String, Arc<ProviderNode>>>,
+}
+
+impl HealthChecker {
+    pub fn new(config: HealthCheckConfig) -> Self {
+        HealthChecker {
+            config,
+            pool: None,
+            nodes: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Checks the providers over the connections of `pool` instead of the shared pool.
+    pub fn with_pool(mut self, pool: ConnectionPool) -> Self {
+        self.pool = Some(pool);
+        self
+    }
+
+    /// The node of the provider at `address`, if it is under check.
+    pub fn node(&self, address: &str) -> Option<Arc<ProviderNode>> {
+        self.nodes.read().unwrap().get(address).cloned()
+    }
+
+    /// Removes the unavailable providers from `providers`, and starts or stops checking the
+    /// providers joining or leaving the list.
+    pub fn filter(&self, providers: Vec<Url>) -> Vec<Url> {
+        let addresses: Vec<String> = providers.iter().map(|url| url.get_ip_port()).collect();
+        let changed = {
+            let nodes = self.nodes.read().unwrap();
+            nodes.len() != addresses.len() || addresses.iter().any(|a| !nodes.contains_key(a))
+        };
+        if changed {
+            self.update_nodes(&providers);
+        }
+
+        let nodes = self.nodes.read().unwrap();
+        providers
+            .into_iter()
+            .zip(addresses.iter())
+            .filter(|(_, address)| nodes.get(*address).is_none_or(|node| node.is_available()))
+            .map(|(url, _)| url)
+            .collect()
+    }
+
+    fn update_nodes(&self, providers: &[Url]) {
+        let runtime = match tokio::runtime::Handle::try_current() {
+            Ok(v) => v,
+            Err(_) => {
+                tracing::warn!("health check needs a tokio runtime, providers are not checked");
+                return;
+            }
+        };
+        let mut nodes = self.nodes.write().unwrap();
+        let mut current = HashMap::with_capacity(providers.len());
+        for url in providers.iter() {
+            let address = url.get_ip_port();
+            let node = match nodes.remove(&address) {
+                Some(node) => node,
+                None => {
+                    let node = Arc::new(ProviderNode::new(url.clone()));
+                    runtime.spawn(check_loop(
+                        node.clone(),
+                        self.config.clone(),
+                        self.pool.clone(),
+                    ));
+                    node
+                }
+            };
+            current.insert(address, node);
+        }
+        // the providers left the list
+        for (_, node) in nodes.drain() {
+            node.destroy();
+        }
+        *nodes = current;
+    }
+}
+
+impl Drop for HealthChecker {
+    fn drop(&mut self) {
+        for node in self.nodes.read().unwrap().values() {
+            node.destroy();
+        }
+    }
+}
+
+async fn check_loop(
+    node: Arc<ProviderNode>,
+    config: HealthCheckConfig,
+    pool: Option<ConnectionPool>,
+) {
+    let url = node.get_url();
+    let uri = match http::Uri::from_str(&format!("http://{}:{}", url.ip, url.port)) {
+        Ok(v) => v,
+        Err(err) => {
+            tracing::error!("invalid provider address {}: {}", url.get_ip_port(), err);
+            return;
+        }
+    };
+    // one attempt per check, the thresholds take care of the flaps
+    let mut check_url = Url::new();
+    check_url.set_param(CLUSTER_KEY, "failfast");
+    let mut invoker = ClusterInvoker::with_directory(Box::new(StaticDirectory::from_uri(&uri)));
+    if let Some(pool) = pool {
+        invoker = invoker.with_pool(pool);
+    }
+    let mut client =
+        HealthClient::new(ClientBuilder::new()).with_cluster(invoker.with_checked_url(check_url));
+
+    let mut interval = tokio::time::interval(config.interval);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    while !node.is_destroyed() {
+        interval.tick().await;
+        let request = Request::new(HealthCheckRequest {
+            service: config.service.clone(),
+        });
+        let serving = match tokio::time::timeout(config.timeout, client.check(request)).await {
+            Ok(Ok(resp)) => {
+                let (_, message) = resp.into_parts();
+                message.status() == ServingStatus::Serving
+            }
+            Ok(Err(status)) if status.code() == Code::Unimplemented => true,
+            Ok(Err(status)) => {
+                tracing::debug!("health check of {} failed: {}", url.get_ip_port(), status);
+                false
+            }
+            Err(_) => {
+                tracing::debug!("health check of {} timed out", url.get_ip_port());
+                false
+            }
+        };
+        if !node.is_destroyed() {
+            node.report(serving, &config);
+        }
+    }
+}
+
+/// Lists the providers of the inner directory except the ones failing the health check.
+#[derive(Debug, Clone)]
+pub struct HealthCheckDirectory {
+    inner: Arc<Box<dyn Directory>>,
+    checker: Arc<HealthChecker>,
+}
+
+impl HealthCheckDirectory {
+    pub fn new(inner: Arc<Box<dyn Directory>>, checker: Arc<HealthChecker>) -> Self {
+        HealthCheckDirectory { inner, checker }
+    }
+}
+
+impl Directory for HealthCheckDirectory {
+    fn list(&self, invocation: Arc<RpcInvocation>) -> Vec<Url> {
+        self.checker.filter(self.inner.list(invocation))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use dubbo_base::constants::{HEALTH_CHECK_KEY, HEALTH_CHECK_UNHEALTHY_THRESHOLD_KEY};
+
+    use crate::{
+        cluster::support::test_util::{TestDirectory, TestProvider},
+        triple::transport::connector::tls_connector::ClientTlsConfig,
+        utils::tls::{load_certs, load_key, server_config},
+    };
+
+    use super::*;
+
+    fn testdata(name: &str) -> std::path::PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("testdata/tls")
+            .join(name)
+    }
+
+    fn config() -> HealthCheckConfig {
+        HealthCheckConfig {
+            interval: Duration::from_millis(20),
+            timeout: Duration::from_millis(500),
+            healthy_threshold: 2,
+            unhealthy_threshold: 3,
+            service: String::new(),
+        }
+    }
+
+    #[test]
+    fn thresholds() {
+        let config = config();
+        let node = ProviderNode::new(Url::from_url("tri://10.0.10.1:20000/test.Health").unwrap());
+        node.report(false, &config);
+        node.report(false, &config);
+        assert!(node.is_available());
+        node.report(false, &config);
+        assert!(!node.is_available());
+
+        node.report(true, &config);
+        assert!(!node.is_available());
+        // a failed check resets the passes
+        node.report(false, &config);
+        node.report(true, &config);
+        assert!(!node.is_available());
+        node.report(true, &config);
+        assert!(node.is_available());
+    }
+
+    #[tokio::test]
+    async fn filter_unhealthy_providers() {
+        let unhealthy = TestProvider::start(Code::Unavailable).await;
+        // does not implement the health service
+        let healthy = TestProvider::start(Code::Unimplemented).await;
+        let checker = Arc::new(HealthChecker::new(config()));
+        let providers = vec![unhealthy.url.clone(), healthy.url.clone()];
+        let directory = HealthCheckDirectory::new(
+            Arc::new(Box::new(TestDirectory(providers.clone()))),
+            checker.clone(),
+        );
+        let invocation = Arc::new(RpcInvocation::default());
+        assert_eq!(directory.list(invocation.clone()), providers);
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(directory.list(invocation), vec![healthy.url.clone()]);
+        assert!(unhealthy.calls() >= 3);
+
+        // stops checking the providers leaving the list
+        let node = checker.node(&unhealthy.url.get_ip_port()).unwrap();
+        checker.filter(vec![healthy.url.clone()]);
+        assert!(node.is_destroyed());
+        assert!(checker.node(&unhealthy.url.get_ip_port()).is_none());
+    }
+
+    #[tokio::test]
+    async fn tls_reference_keeps_healthy_providers() {
+        let config = server_config(
+            load_certs(&testdata("server.pem")).unwrap(),
+            load_key(&testdata("server.key")).unwrap(),
+            None,
+        )
+        .unwrap();
+        // does not implement the health service
+        let provider = TestProvider::start_tls(Code::Unimplemented, Arc::new(config)).await;
+        let tls = ClientTlsConfig::new()
+            .with_ca_certs(load_certs(&testdata("ca.pem")).unwrap())
+            .with_server_name("localhost".to_string())
+            .connector()
+            .unwrap();
+
+        let mut url = Url::new();
+        url.set_param(HEALTH_CHECK_KEY, "true");
+        url.set_param(HEALTH_CHECK_INTERVAL_KEY, "20");
+        url.set_param(HEALTH_CHECK_UNHEALTHY_THRESHOLD_KEY, "2");
+        let invoker =
+            ClusterInvoker::with_directory(Box::new(TestDirectory(vec![provider.url.clone()])))
+                .with_pool(ConnectionPool::new("http").with_tls(tls))
+                .with_url(url)
+                .unwrap();
+        let invocation = Arc::new(RpcInvocation::default());
+        assert_eq!(invoker.list(invocation.clone()), vec![provider.url.clone()]);
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert!(provider.calls() >= 3);
+        assert_eq!(invoker.list(invocation), vec![provider.url.clone()]);
+    }
+}
@@ -0,0 +1,375 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use bytes::BytesMut;
+use dubbo_base::{
+    constants::{HASH_FIELD_KEY, HASH_KEY, HASH_NODES_KEY},
+    Url,
+};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    fmt::{Debug, Formatter},
+    sync::{Arc, RwLock},
+};
+
+use crate::{
+    cluster::loadbalance::types::{LoadBalance, Metadata},
+    codegen::RpcInvocation,
+    invocation::Invocation,
+    triple::compression::{decompress, CompressionEncoding},
+};
+
+pub const DEFAULT_HASH_NODES: usize = 160;
+/// Rings cached at most, one for each method.
+pub const MAX_SELECTORS: usize = 1024;
+
+/// Sends the requests with the same hash key to the same provider. The key is the request
+/// metadata named by the `hash.key` param, else the field numbered by the `hash.field` param
+/// of the protobuf request message, else the whole request message, uncompressed. The field is
+/// looked up among the top level fields only, a nested one has to be copied into the metadata.
+/// Streaming requests without the metadata key all hash to the same provider.
+pub struct ConsistentHashLoadBalance {
+    pub metadata: Metadata,
+    pub selectors: RwLock<HashMap<String, Arc<ConsistentHashSelector>>>,
+}
+
+impl Default for ConsistentHashLoadBalance {
+    fn default() -> Self {
+        ConsistentHashLoadBalance {
+            metadata: Metadata::new("consistenthash"),
+            selectors: RwLock::new(HashMap::new()),
+        }
+    }
+}
+
+impl Debug for ConsistentHashLoadBalance {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ConsistentHashLoadBalance")
+    }
+}
+
+impl LoadBalance for ConsistentHashLoadBalance {
+    fn select(
+        &self,
+        invokers: Arc<Vec<Url>>,
+        url: Option<Url>,
+        invocation: Arc<RpcInvocation>,
+    ) -> Option<Url> {
+        if invokers.is_empty() {
+            return None;
+        }
+        let method = invocation.get_method_name();
+        let param = |key: &str| {
+            url.as_ref()
+                .and_then(|url| url.get_method_param(&method, key))
+        };
+        let replicas = param(HASH_NODES_KEY)
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(DEFAULT_HASH_NODES);
+
+        let fingerprint = invocation.unique_fingerprint();
+        let identity = ConsistentHashSelector::identity(&invokers);
+        let cached = self.selectors.read().unwrap().get(&fingerprint).cloned();
+        let selector = match cached {
+            Some(selector) if selector.identity == identity && selector.replicas == replicas => {
+                selector
+            }
+            _ => {
+                let selector = Arc::new(ConsistentHashSelector::new(&invokers, replicas));
+                let mut selectors = self.selectors.write().unwrap();
+                if selectors.len() >= MAX_SELECTORS && !selectors.contains_key(&fingerprint) {
+                    let evicted = selectors.keys().next().cloned();
+                    if let Some(evicted) = evicted {
+                        selectors.remove(&evicted);
+                    }
+                }
+                selectors.insert(fingerprint, selector.clone());
+                selector
+            }
+        };
+
+        let message = || {
+            message_key(
+                invocation
+                    .get_arguments()
+                    .map(|v| v.as_ref())
+                    .unwrap_or_default(),
+            )
+        };
+        let field = param(HASH_FIELD_KEY).and_then(|v| v.parse::<u32>().ok());
+        let key = match (param(HASH_KEY), field) {
+            (Some(key), _) => Cow::Borrowed(
+                invocation
+                    .get_attachment(&key)
+                    .unwrap_or_default()
+                    .as_bytes(),
+            ),
+            (None, Some(field)) => {
+                let message = message();
+                let value = field_value(&message, field).unwrap_or_default().to_vec();
+                Cow::Owned(value)
+            }
+            (None, None) => message(),
+        };
+        selector.select(&key)
+    }
+}
+
+/// The request message of the encoded `arguments` frame, uncompressed so that the same message
+/// hashes the same whether the request is compressed or not.
+fn message_key(arguments: &[u8]) -> Cow<'_, [u8]> {
+    // compressed flag and message length
+    if arguments.len() < 5 {
+        return Cow::Borrowed(arguments);
+    }
+    let message = &arguments[5..];
+    if arguments[0] == 0 {
+        return Cow::Borrowed(message);
+    }
+    let mut src = BytesMut::from(message);
+    let mut dst = BytesMut::new();
+    match decompress(CompressionEncoding::Gzip, &mut src, &mut dst, message.len()) {
+        Ok(_) => Cow::Owned(dst.to_vec()),
+        Err(_) => Cow::Borrowed(message),
+    }
+}
+
+/// The encoded value of the last top level field numbered `field` of a protobuf `message`,
+/// `None` when the message has none or is not valid protobuf.
+fn field_value(message: &[u8], field: u32) -> Option<&[u8]> {
+    fn varint(buf: &[u8], pos: &mut usize) -> Option<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let b = *buf.get(*pos)?;
+            *pos += 1;
+            value |= ((b & 0x7f) as u64) << shift;
+            if b & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    }
+    let mut found = None;
+    let mut pos = 0;
+    while pos < message.len() {
+        let tag = varint(message, &mut pos)?;
+        let (start, end) = match tag & 0x7 {
+            0 => {
+                let start = pos;
+                varint(message, &mut pos)?;
+                (start, pos)
+            }
+            1 => (pos, pos.checked_add(8)?),
+            2 => {
+                let len = varint(message, &mut pos)? as usize;
+                (pos, pos.checked_add(len)?)
+            }
+            5 => (pos, pos.checked_add(4)?),
+            // the deprecated groups
+            _ => return None,
+        };
+        let value = message.get(start..end)?;
+        if tag >> 3 == field as u64 {
+            found = Some(value);
+        }
+        pos = end;
+    }
+    found
+}
+
+/// The virtual node ring of a provider list, rebuilt only when the provider list changes.
+pub struct ConsistentHashSelector {
+    identity: u64,
+    replicas: usize,
+    nodes: BTreeMap<u64, Url>,
+}
+
+impl ConsistentHashSelector {
+    fn new(invokers: &[Url], replicas: usize) -> Self {
+        let mut nodes = BTreeMap::new();
+        for invoker in invokers.iter() {
+            let address = invoker.get_ip_port();
+            for i in 0..replicas.max(1) {
+                let hash = hash(format!("{}-{}", address, i).as_bytes());
+                nodes.insert(hash, invoker.clone());
+            }
+        }
+        ConsistentHashSelector {
+            identity: Self::identity(invokers),
+            replicas,
+            nodes,
+        }
+    }
+
+    fn identity(invokers: &[Url]) -> u64 {
+        let mut addresses: Vec<String> = invokers.iter().map(|url| url.get_ip_port()).collect();
+        addresses.sort();
+        hash(addresses.join(",").as_bytes())
+    }
+
+    fn select(&self, key: &[u8]) -> Option<Url> {
+        let hash = hash(key);
+        self.nodes
+            .range(hash..)
+            .next()
+            .or_else(|| self.nodes.iter().next())
+            .map(|(_, url)| url.clone())
+    }
+}
+
+// FNV-1a with a murmur3 finalizer, stable across processes so that all consumers agree on the ring.
+fn hash(bytes: &[u8]) -> u64 {
+    let mut h: u64 = 0xcbf29ce484222325;
+    for b in bytes {
+        h ^= *b as u64;
+        h = h.wrapping_mul(0x100000001b3);
+    }
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xff51afd7ed558ccd);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xc4ceb9fe1a85ec53);
+    h ^= h >> 33;
+    h
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::invocation::Metadata as RequestMetadata;
+
+    fn provider(port: u16) -> Url {
+        Url::from_url(&format!("tri://127.0.0.1:{}/Greeter", port)).unwrap()
+    }
+
+    fn invocation(tenant: &str) -> Arc<RpcInvocation> {
+        let mut headers = http::HeaderMap::new();
+        headers.insert("tenant", tenant.parse().unwrap());
+        Arc::new(
+            RpcInvocation::default()
+                .with_service_unique_name("Greeter".to_string())
+                .with_method_name("greet".to_string())
+                .with_metadata(&RequestMetadata::from_headers(headers)),
+        )
+    }
+
+    #[test]
+    fn test_select_sticky_by_metadata() {
+        let lb = ConsistentHashLoadBalance::default();
+        let mut url = Url::from_url("tri://127.0.0.1:8888/Greeter").unwrap();
+        url.set_param(HASH_KEY, "tenant");
+        let invokers = Arc::new((8000..8005).map(provider).collect::<Vec<_>>());
+
+        let selected = lb.select(invokers.clone(), Some(url.clone()), invocation("a"));
+        for _ in 0..10 {
+            let again = lb.select(invokers.clone(), Some(url.clone()), invocation("a"));
+            assert_eq!(again, selected);
+        }
+        // only the keys of the removed provider move
+        let remaining: Vec<Url> = invokers
+            .iter()
+            .filter(|v| Some(*v) != selected.as_ref())
+            .cloned()
+            .collect();
+        let other = (0..50)
+            .map(|i| format!("tenant-{}", i))
+            .find(|t| lb.select(invokers.clone(), Some(url.clone()), invocation(t)) != selected)
+            .unwrap();
+        let before = lb.select(invokers.clone(), Some(url.clone()), invocation(&other));
+        let after = lb.select(Arc::new(remaining), Some(url), invocation(&other));
+        assert_eq!(before, after);
+    }
+
+    #[test]
+    fn test_message_key_ignores_compression() {
+        let message = b"greet alice".to_vec();
+        let mut plain = vec![0u8];
+        plain.extend_from_slice(&(message.len() as u32).to_be_bytes());
+        plain.extend_from_slice(&message);
+
+        let mut src = BytesMut::from(&message[..]);
+        let mut compressed = BytesMut::new();
+        crate::triple::compression::compress(
+            CompressionEncoding::Gzip,
+            &mut src,
+            &mut compressed,
+            message.len(),
+        )
+        .unwrap();
+        let mut gzip = vec![1u8];
+        gzip.extend_from_slice(&(compressed.len() as u32).to_be_bytes());
+        gzip.extend_from_slice(&compressed);
+
+        assert_eq!(message_key(&plain), &message[..]);
+        assert_eq!(message_key(&gzip), &message[..]);
+    }
+
+    #[test]
+    fn test_selectors_bounded() {
+        let lb = ConsistentHashLoadBalance::default();
+        let invokers = Arc::new(vec![provider(8000)]);
+        for i in 0..MAX_SELECTORS + 10 {
+            let invocation = RpcInvocation::default()
+                .with_service_unique_name("Greeter".to_string())
+                .with_method_name(format!("method{}", i));
+            lb.select(invokers.clone(), None, Arc::new(invocation));
+        }
+        assert_eq!(lb.selectors.read().unwrap().len(), MAX_SELECTORS);
+    }
+
+    #[test]
+    fn test_select_by_field() {
+        // field 1 is a varint of the request number, field 2 the string "tenant-{n}"
+        let arguments = |request: u8, tenant: &str| {
+            let mut message = vec![0x08, request, 0x12, tenant.len() as u8];
+            message.extend_from_slice(tenant.as_bytes());
+            let mut frame = vec![0u8];
+            frame.extend_from_slice(&(message.len() as u32).to_be_bytes());
+            frame.extend_from_slice(&message);
+            bytes::Bytes::from(frame)
+        };
+        let invocation = |request: u8, tenant: &str| {
+            Arc::new(
+                RpcInvocation::default()
+                    .with_service_unique_name("Greeter".to_string())
+                    .with_method_name("greet".to_string())
+                    .with_arguments(arguments(request, tenant)),
+            )
+        };
+        assert_eq!(field_value(&arguments(7, "a")[5..], 2), Some(&b"a"[..]));
+        assert_eq!(field_value(&arguments(7, "a")[5..], 1), Some(&[7u8][..]));
+        assert_eq!(field_value(&arguments(7, "a")[5..], 3), None);
+        assert_eq!(field_value(&[0x12, 0x05, b'a'], 2), None);
+
+        let lb = ConsistentHashLoadBalance::default();
+        let mut url = Url::from_url("tri://127.0.0.1:8888/Greeter").unwrap();
+        url.set_param(HASH_FIELD_KEY, "2");
+        let invokers = Arc::new((8000..8005).map(provider).collect::<Vec<_>>());
+        for tenant in ["tenant-1", "tenant-2", "tenant-3"] {
+            let selected = lb.select(invokers.clone(), Some(url.clone()), invocation(0, tenant));
+            // the other fields of the message do not move the request
+            for request in 1..10 {
+                let again = lb.select(
+                    invokers.clone(),
+                    Some(url.clone()),
+                    invocation(request, tenant),
+                );
+                assert_eq!(again, selected);
+            }
+        }
+    }
+}
@@ -0,0 +1,135 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use dubbo_base::Url;
+use std::{
+    fmt::{Debug, Formatter},
+    sync::Arc,
+};
+
+use crate::{
+    cluster::loadbalance::{
+        get_weight, random_by_weight,
+        types::{LoadBalance, Metadata},
+    },
+    codegen::RpcInvocation,
+    invocation::Invocation,
+    triple::client::rpc_status::RpcStatus,
+};
+
+/// Picks the provider with the fewest in-flight calls of the method, ties are broken randomly
+/// by weight.
+pub struct LeastActiveLoadBalance {
+    pub metadata: Metadata,
+}
+
+impl Default for LeastActiveLoadBalance {
+    fn default() -> Self {
+        LeastActiveLoadBalance {
+            metadata: Metadata::new("leastactive"),
+        }
+    }
+}
+
+impl Debug for LeastActiveLoadBalance {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "LeastActiveLoadBalance")
+    }
+}
+
+impl LoadBalance for LeastActiveLoadBalance {
+    fn select(
+        &self,
+        invokers: Arc<Vec<Url>>,
+        _url: Option<Url>,
+        invocation: Arc<RpcInvocation>,
+    ) -> Option<Url> {
+        if invokers.is_empty() {
+            return None;
+        }
+        let method = invocation.get_method_name();
+        let weights: Vec<usize> = invokers
+            .iter()
+            .map(|invoker| get_weight(invoker, &method))
+            .collect();
+        let mut least_active = usize::MAX;
+        let mut least_indexes = Vec::with_capacity(invokers.len());
+        for (index, invoker) in invokers.iter().enumerate() {
+            let active = RpcStatus::get(invoker, &method).active();
+            if active < least_active {
+                least_active = active;
+                least_indexes.clear();
+            }
+            if active == least_active {
+                least_indexes.push(index);
+            }
+        }
+        let index = random_by_weight(&least_indexes, &weights);
+        Some(invokers[index].clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dubbo_base::constants::WEIGHT_KEY;
+
+    use super::*;
+
+    const METHOD: &str = "leastactive";
+
+    fn provider(host: &str) -> Url {
+        Url::from_url(&format!("tri://{}:20000/test.LeastActive", host)).unwrap()
+    }
+
+    fn select(invokers: &[Url]) -> Url {
+        let invocation = RpcInvocation::default().with_method_name(METHOD.to_string());
+        LeastActiveLoadBalance::default()
+            .select(Arc::new(invokers.to_vec()), None, Arc::new(invocation))
+            .unwrap()
+    }
+
+    #[test]
+    fn pick_least_active() {
+        let invokers = vec![
+            provider("10.0.5.1"),
+            provider("10.0.5.2"),
+            provider("10.0.5.3"),
+        ];
+        let _calls = [
+            RpcStatus::begin_count(&invokers[0], METHOD),
+            RpcStatus::begin_count(&invokers[0], METHOD),
+            RpcStatus::begin_count(&invokers[1], METHOD),
+        ];
+        for _ in 0..10 {
+            assert_eq!(select(&invokers), invokers[2]);
+        }
+    }
+
+    #[test]
+    fn tie_broken_by_weight() {
+        let mut light = provider("10.0.6.1");
+        light.set_param(WEIGHT_KEY, "0");
+        let heavy = provider("10.0.6.2");
+        let invokers = vec![light, heavy.clone()];
+        for _ in 0..10 {
+            assert_eq!(select(&invokers), heavy);
+        }
+        // a less active provider wins over the heavier one
+        let _call = RpcStatus::begin_count(&heavy, METHOD);
+        assert_eq!(select(&invokers), invokers[0]);
+    }
+}
@@ -14,5 +14,9 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+pub mod consistenthash;
+pub mod leastactive;
+pub mod p2c;
 pub mod random;
 pub mod roundrobin;
+pub mod shortestresponse;
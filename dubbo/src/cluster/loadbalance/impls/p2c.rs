@@ -0,0 +1,151 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use dubbo_base::Url;
+use std::{
+    fmt::{Debug, Formatter},
+    sync::Arc,
+};
+
+use crate::{
+    cluster::loadbalance::{
+        get_weight,
+        types::{LoadBalance, Metadata},
+    },
+    codegen::RpcInvocation,
+    invocation::Invocation,
+    triple::client::rpc_status::RpcStatus,
+};
+
+/// Power of two choices: picks two providers at random and takes the one with the lower cost,
+/// which is the latency ewma multiplied by the in-flight calls and divided by the weight.
+pub struct P2cLoadBalance {
+    pub metadata: Metadata,
+}
+
+impl Default for P2cLoadBalance {
+    fn default() -> Self {
+        P2cLoadBalance {
+            metadata: Metadata::new("p2c"),
+        }
+    }
+}
+
+impl Debug for P2cLoadBalance {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "P2cLoadBalance")
+    }
+}
+
+fn cost(invoker: &Url, method: &str) -> f64 {
+    let status = RpcStatus::get(invoker, method);
+    // one microsecond floor, so that the providers without samples still compare by load
+    let latency = status.ewma_latency().as_micros() as f64 + 1.0;
+    let weight = get_weight(invoker, method).max(1) as f64;
+    latency * (status.active() + 1) as f64 / weight
+}
+
+impl LoadBalance for P2cLoadBalance {
+    fn select(
+        &self,
+        invokers: Arc<Vec<Url>>,
+        _url: Option<Url>,
+        invocation: Arc<RpcInvocation>,
+    ) -> Option<Url> {
+        match invokers.len() {
+            0 => return None,
+            1 => return Some(invokers[0].clone()),
+            _ => {}
+        }
+        let method = invocation.get_method_name();
+        let first = rand::random::<usize>() % invokers.len();
+        let mut second = rand::random::<usize>() % (invokers.len() - 1);
+        if second >= first {
+            second += 1;
+        }
+        if cost(&invokers[first], &method) <= cost(&invokers[second], &method) {
+            Some(invokers[first].clone())
+        } else {
+            Some(invokers[second].clone())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::status::Code;
+    use dubbo_base::constants::WEIGHT_KEY;
+
+    use super::*;
+
+    const METHOD: &str = "p2c";
+
+    fn provider(host: &str) -> Url {
+        Url::from_url(&format!("tri://{}:20000/test.P2c", host)).unwrap()
+    }
+
+    fn select(invokers: &[Url]) -> Url {
+        let invocation = RpcInvocation::default().with_method_name(METHOD.to_string());
+        P2cLoadBalance::default()
+            .select(Arc::new(invokers.to_vec()), None, Arc::new(invocation))
+            .unwrap()
+    }
+
+    #[test]
+    fn pick_lower_latency() {
+        let failing = provider("10.0.9.1");
+        let healthy = provider("10.0.9.2");
+        RpcStatus::begin_count(&failing, METHOD).end(Code::Unavailable);
+        RpcStatus::begin_count(&healthy, METHOD).end(Code::Ok);
+        for _ in 0..10 {
+            assert_eq!(select(&[failing.clone(), healthy.clone()]), healthy);
+        }
+    }
+
+    #[test]
+    fn application_errors_not_penalized() {
+        let not_found = provider("10.0.9.7");
+        let failing = provider("10.0.9.8");
+        RpcStatus::begin_count(&not_found, METHOD).end(Code::NotFound);
+        RpcStatus::begin_count(&failing, METHOD).end(Code::Unavailable);
+        for _ in 0..10 {
+            assert_eq!(select(&[not_found.clone(), failing.clone()]), not_found);
+        }
+    }
+
+    #[test]
+    fn pick_less_loaded() {
+        let busy = provider("10.0.9.3");
+        let idle = provider("10.0.9.4");
+        let _calls: Vec<_> = (0..3)
+            .map(|_| RpcStatus::begin_count(&busy, METHOD))
+            .collect();
+        for _ in 0..10 {
+            assert_eq!(select(&[busy.clone(), idle.clone()]), idle);
+        }
+    }
+
+    #[test]
+    fn cost_divided_by_weight() {
+        let mut heavy = provider("10.0.9.5");
+        heavy.set_param(WEIGHT_KEY, "400");
+        let light = provider("10.0.9.6");
+        RpcStatus::begin_count(&heavy, METHOD).end(Code::Internal);
+        RpcStatus::begin_count(&light, METHOD).end(Code::Internal);
+        assert!(cost(&heavy, METHOD) < cost(&light, METHOD));
+    }
+}
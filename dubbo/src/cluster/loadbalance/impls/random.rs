@@ -21,8 +21,12 @@ use std::{
 };
 
 use crate::{
-    cluster::loadbalance::types::{LoadBalance, Metadata},
+    cluster::loadbalance::{
+        get_weight, random_by_weight,
+        types::{LoadBalance, Metadata},
+    },
     codegen::RpcInvocation,
+    invocation::Invocation,
 };
 
 pub struct RandomLoadBalance {
@@ -48,12 +52,18 @@ impl LoadBalance for RandomLoadBalance {
         &self,
         invokers: Arc<Vec<Url>>,
         _url: Option<Url>,
-        _invocation: Arc<RpcInvocation>,
+        invocation: Arc<RpcInvocation>,
     ) -> Option<Url> {
         if invokers.is_empty() {
             return None;
         }
-        let index = rand::random::<usize>() % invokers.len();
+        let method = invocation.get_method_name();
+        let weights: Vec<usize> = invokers
+            .iter()
+            .map(|invoker| get_weight(invoker, &method))
+            .collect();
+        let indexes: Vec<usize> = (0..invokers.len()).collect();
+        let index = random_by_weight(&indexes, &weights);
         Some(invokers[index].clone())
     }
 }
@@ -14,31 +14,46 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+
 use dubbo_base::Url;
 use std::{
     collections::HashMap,
     fmt::{Debug, Formatter},
-    sync::{
-        atomic::{AtomicUsize, Ordering},
-        Arc, RwLock,
-    },
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
 };
 
 use crate::{
-    cluster::loadbalance::types::{LoadBalance, Metadata},
+    cluster::loadbalance::{
+        get_weight,
+        types::{LoadBalance, Metadata},
+    },
     codegen::RpcInvocation,
+    invocation::Invocation,
 };
 
+// the state of a provider not selectable for this long is dropped
+const RECYCLE_PERIOD: Duration = Duration::from_secs(60);
+
+/// Smooth weighted round robin, as nginx does: every pick adds the weight to the current
+/// weight of each provider, then selects the highest and subtracts the total weight from it.
 pub struct RoundRobinLoadBalance {
     pub metadata: Metadata,
-    pub counter_map: RwLock<HashMap<String, AtomicUsize>>,
+    // keyed by service and method, then by provider
+    weighted_map: Mutex<HashMap<String, HashMap<String, WeightedRoundRobin>>>,
+}
+
+struct WeightedRoundRobin {
+    weight: i64,
+    current: i64,
+    last_update: Instant,
 }
 
 impl Default for RoundRobinLoadBalance {
     fn default() -> Self {
         RoundRobinLoadBalance {
             metadata: Metadata::new("roundrobin"),
-            counter_map: RwLock::new(HashMap::new()),
+            weighted_map: Mutex::new(HashMap::new()),
         }
     }
 }
@@ -49,18 +64,6 @@ impl Debug for RoundRobinLoadBalance {
     }
 }
 
-impl RoundRobinLoadBalance {
-    fn guarantee_counter_key(&self, key: &str) {
-        let contained = self.counter_map.try_read().unwrap().contains_key(key);
-        if !contained {
-            self.counter_map
-                .try_write()
-                .unwrap()
-                .insert(key.to_string(), AtomicUsize::new(0));
-        }
-    }
-}
-
 impl LoadBalance for RoundRobinLoadBalance {
     fn select(
         &self,
@@ -71,15 +74,78 @@ impl LoadBalance for RoundRobinLoadBalance {
         if invokers.is_empty() {
             return None;
         }
-        let fingerprint = invocation.unique_fingerprint();
-        self.guarantee_counter_key(fingerprint.as_str());
-        let index = self
-            .counter_map
-            .try_read()
-            .unwrap()
-            .get(fingerprint.as_str())?
-            .fetch_add(1, Ordering::SeqCst)
-            % invokers.len();
+        let method = invocation.get_method_name();
+        let now = Instant::now();
+        let mut weighted_map = self.weighted_map.lock().unwrap();
+        let weighted = weighted_map
+            .entry(invocation.unique_fingerprint())
+            .or_default();
+
+        let mut total_weight = 0;
+        let mut selected: Option<(usize, String)> = None;
+        let mut max_current = i64::MIN;
+        for (index, invoker) in invokers.iter().enumerate() {
+            let weight = get_weight(invoker, &method) as i64;
+            let key = invoker.to_url();
+            let state = weighted
+                .entry(key.clone())
+                .or_insert_with(|| WeightedRoundRobin {
+                    weight,
+                    current: 0,
+                    last_update: now,
+                });
+            if state.weight != weight {
+                // the weight changed, eg. during warmup
+                state.weight = weight;
+                state.current = 0;
+            }
+            state.current += weight;
+            state.last_update = now;
+            if state.current > max_current {
+                max_current = state.current;
+                selected = Some((index, key));
+            }
+            total_weight += weight;
+        }
+        if weighted.len() > invokers.len() {
+            weighted.retain(|_, state| now.duration_since(state.last_update) < RECYCLE_PERIOD);
+        }
+
+        let (index, key) = selected?;
+        if let Some(state) = weighted.get_mut(&key) {
+            state.current -= total_weight;
+        }
         Some(invokers[index].clone())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_smooth_weighted_round_robin() {
+        let lb = RoundRobinLoadBalance::default();
+        let invokers = Arc::new(vec![
+            Url::from_url("tri://127.0.0.1:8001/Greeter?weight=5").unwrap(),
+            Url::from_url("tri://127.0.0.1:8002/Greeter?weight=1").unwrap(),
+            Url::from_url("tri://127.0.0.1:8003/Greeter?weight=1").unwrap(),
+        ]);
+        let invocation = Arc::new(
+            RpcInvocation::default()
+                .with_service_unique_name("Greeter".to_string())
+                .with_method_name("greet".to_string()),
+        );
+        let ports: Vec<String> = (0..7)
+            .map(|_| {
+                lb.select(invokers.clone(), None, invocation.clone())
+                    .unwrap()
+                    .port
+            })
+            .collect();
+        assert_eq!(
+            ports,
+            vec!["8001", "8001", "8002", "8001", "8003", "8001", "8001"]
+        );
+    }
+}
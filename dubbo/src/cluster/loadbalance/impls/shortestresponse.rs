@@ -0,0 +1,214 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use dubbo_base::Url;
+use std::{
+    collections::HashMap,
+    fmt::{Debug, Formatter},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    cluster::loadbalance::{
+        get_weight, random_by_weight,
+        types::{LoadBalance, Metadata},
+    },
+    codegen::RpcInvocation,
+    invocation::Invocation,
+    triple::client::rpc_status::{RpcStatus, FAILURE_PENALTY},
+};
+
+// the statistics older than the window are forgotten
+const SLIDE_PERIOD: Duration = Duration::from_secs(30);
+
+/// Picks the provider with the shortest estimated response time, which is the average latency
+/// of the calls in the last window multiplied by the in-flight calls. A call failed by the
+/// provider, eg. `Unavailable` but not `NotFound`, counts as one second, so that a failing
+/// provider is not preferred. Ties are broken randomly by weight.
+pub struct ShortestResponseLoadBalance {
+    pub metadata: Metadata,
+    window: Mutex<SlideWindow>,
+}
+
+struct SlideWindow {
+    start: Instant,
+    // statistics at the start of the window, keyed by provider and method
+    offsets: HashMap<String, Offset>,
+}
+
+#[derive(Clone, Copy)]
+struct Offset {
+    succeeded: u64,
+    succeeded_elapsed: Duration,
+    failed: u64,
+}
+
+impl Default for ShortestResponseLoadBalance {
+    fn default() -> Self {
+        ShortestResponseLoadBalance {
+            metadata: Metadata::new("shortestresponse"),
+            window: Mutex::new(SlideWindow {
+                start: Instant::now(),
+                offsets: HashMap::new(),
+            }),
+        }
+    }
+}
+
+impl Debug for ShortestResponseLoadBalance {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ShortestResponseLoadBalance")
+    }
+}
+
+impl ShortestResponseLoadBalance {
+    fn estimate_response(&self, invoker: &Url, method: &str) -> Duration {
+        let status = RpcStatus::get(invoker, method);
+        let current = Offset {
+            succeeded: status.succeeded(),
+            succeeded_elapsed: status.succeeded_elapsed(),
+            failed: status.failed(),
+        };
+        let mut window = self.window.lock().unwrap();
+        if window.start.elapsed() > SLIDE_PERIOD {
+            window.start = Instant::now();
+            window.offsets.clear();
+        }
+        let offset = *window
+            .offsets
+            .entry(format!("{}#{}", invoker.short_url(), method))
+            .or_insert(current);
+        drop(window);
+
+        let succeeded = current.succeeded.saturating_sub(offset.succeeded);
+        let failed = current.failed.saturating_sub(offset.failed);
+        let count = succeeded + failed;
+        let average = if count == 0 {
+            Duration::ZERO
+        } else {
+            let elapsed = current
+                .succeeded_elapsed
+                .saturating_sub(offset.succeeded_elapsed)
+                + FAILURE_PENALTY * failed as u32;
+            elapsed / count as u32
+        };
+        average * (status.active() as u32 + 1)
+    }
+}
+
+impl LoadBalance for ShortestResponseLoadBalance {
+    fn select(
+        &self,
+        invokers: Arc<Vec<Url>>,
+        _url: Option<Url>,
+        invocation: Arc<RpcInvocation>,
+    ) -> Option<Url> {
+        if invokers.is_empty() {
+            return None;
+        }
+        let method = invocation.get_method_name();
+        let weights: Vec<usize> = invokers
+            .iter()
+            .map(|invoker| get_weight(invoker, &method))
+            .collect();
+        let mut shortest = Duration::MAX;
+        let mut shortest_indexes = Vec::with_capacity(invokers.len());
+        for (index, invoker) in invokers.iter().enumerate() {
+            let estimate = self.estimate_response(invoker, &method);
+            if estimate < shortest {
+                shortest = estimate;
+                shortest_indexes.clear();
+            }
+            if estimate == shortest {
+                shortest_indexes.push(index);
+            }
+        }
+        let index = random_by_weight(&shortest_indexes, &weights);
+        Some(invokers[index].clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::status::Code;
+
+    use super::*;
+
+    const METHOD: &str = "shortestresponse";
+
+    fn provider(host: &str) -> Url {
+        Url::from_url(&format!("tri://{}:20000/test.ShortestResponse", host)).unwrap()
+    }
+
+    #[test]
+    fn avoid_failing_provider() {
+        let lb = ShortestResponseLoadBalance::default();
+        let failing = provider("10.0.8.1");
+        let healthy = provider("10.0.8.2");
+        let invokers = Arc::new(vec![failing.clone(), healthy.clone()]);
+        let invocation = Arc::new(RpcInvocation::default().with_method_name(METHOD.to_string()));
+        // starts the window of both providers
+        lb.select(invokers.clone(), None, invocation.clone());
+
+        for _ in 0..3 {
+            RpcStatus::begin_count(&failing, METHOD).end(Code::Unavailable);
+            RpcStatus::begin_count(&healthy, METHOD).end(Code::Ok);
+        }
+        for _ in 0..10 {
+            let selected = lb.select(invokers.clone(), None, invocation.clone());
+            assert_eq!(selected, Some(healthy.clone()));
+        }
+    }
+
+    #[test]
+    fn application_errors_not_penalized() {
+        let lb = ShortestResponseLoadBalance::default();
+        let not_found = provider("10.0.8.5");
+        let failing = provider("10.0.8.6");
+        let invokers = Arc::new(vec![not_found.clone(), failing.clone()]);
+        let invocation = Arc::new(RpcInvocation::default().with_method_name(METHOD.to_string()));
+        lb.select(invokers.clone(), None, invocation.clone());
+
+        for _ in 0..3 {
+            RpcStatus::begin_count(&not_found, METHOD).end(Code::NotFound);
+            RpcStatus::begin_count(&failing, METHOD).end(Code::Internal);
+        }
+        assert!(lb.estimate_response(&not_found, METHOD) < FAILURE_PENALTY);
+        for _ in 0..10 {
+            let selected = lb.select(invokers.clone(), None, invocation.clone());
+            assert_eq!(selected, Some(not_found.clone()));
+        }
+    }
+
+    #[test]
+    fn estimate_by_active_calls() {
+        let lb = ShortestResponseLoadBalance::default();
+        let busy = provider("10.0.8.3");
+        let idle = provider("10.0.8.4");
+        lb.estimate_response(&busy, METHOD);
+        lb.estimate_response(&idle, METHOD);
+        RpcStatus::begin_count(&busy, METHOD).end(Code::Internal);
+        RpcStatus::begin_count(&idle, METHOD).end(Code::Internal);
+
+        let _calls: Vec<_> = (0..2)
+            .map(|_| RpcStatus::begin_count(&busy, METHOD))
+            .collect();
+        assert_eq!(lb.estimate_response(&idle, METHOD), FAILURE_PENALTY);
+        assert_eq!(lb.estimate_response(&busy, METHOD), FAILURE_PENALTY * 3);
+    }
+}
@@ -14,12 +14,23 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
+use dubbo_base::{
+    constants::{TIMESTAMP_KEY, WARMUP_KEY, WEIGHT_KEY},
+    Url,
+};
 use lazy_static::lazy_static;
 
 use crate::cluster::loadbalance::{
-    impls::{random::RandomLoadBalance, roundrobin::RoundRobinLoadBalance},
+    impls::{
+        consistenthash::ConsistentHashLoadBalance, leastactive::LeastActiveLoadBalance,
+        p2c::P2cLoadBalance, random::RandomLoadBalance, roundrobin::RoundRobinLoadBalance,
+        shortestresponse::ShortestResponseLoadBalance,
+    },
     types::BoxLoadBalance,
 };
 
@@ -38,5 +49,92 @@ fn init_loadbalance_extensions() -> HashMap<String, BoxLoadBalance> {
         "roundrobin".to_string(),
         Box::new(RoundRobinLoadBalance::default()),
     );
+    loadbalance_map.insert(
+        "leastactive".to_string(),
+        Box::new(LeastActiveLoadBalance::default()),
+    );
+    loadbalance_map.insert(
+        "consistenthash".to_string(),
+        Box::new(ConsistentHashLoadBalance::default()),
+    );
+    loadbalance_map.insert(
+        "shortestresponse".to_string(),
+        Box::new(ShortestResponseLoadBalance::default()),
+    );
+    loadbalance_map.insert("p2c".to_string(), Box::new(P2cLoadBalance::default()));
     loadbalance_map
 }
+
+pub const DEFAULT_WEIGHT: usize = 100;
+
+// in milliseconds
+pub const DEFAULT_WARMUP: u64 = 10 * 60 * 1000;
+
+/// Picks one of `indexes` randomly in proportion to its weight in `weights`.
+pub(crate) fn random_by_weight(indexes: &[usize], weights: &[usize]) -> usize {
+    let total_weight: usize = indexes.iter().map(|index| weights[*index]).sum();
+    let same_weight = indexes
+        .iter()
+        .all(|index| weights[*index] == weights[indexes[0]]);
+    if !same_weight && total_weight > 0 {
+        let mut offset = rand::random::<usize>() % total_weight;
+        for index in indexes.iter() {
+            if offset < weights[*index] {
+                return *index;
+            }
+            offset -= weights[*index];
+        }
+    }
+    indexes[rand::random::<usize>() % indexes.len()]
+}
+
+/// The weight of the provider for `method`, from the `weight` url param.
+///
+/// The weight ramps up linearly over the `warmup` milliseconds after the provider `timestamp`,
+/// so that a freshly started provider is not overloaded before it is warm.
+pub(crate) fn get_weight(url: &Url, method: &str) -> usize {
+    let weight = url
+        .get_method_param(method, WEIGHT_KEY)
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_WEIGHT);
+    if weight == 0 {
+        return 0;
+    }
+    let timestamp = match url
+        .get_param(TIMESTAMP_KEY)
+        .and_then(|v| v.parse::<u64>().ok())
+    {
+        Some(v) if v > 0 => v,
+        _ => return weight,
+    };
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    let warmup = url
+        .get_param(WARMUP_KEY)
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_WARMUP);
+    warmup_weight(weight, now.saturating_sub(timestamp), warmup)
+}
+
+fn warmup_weight(weight: usize, uptime: u64, warmup: u64) -> usize {
+    if uptime >= warmup {
+        return weight;
+    }
+    let ramped = (uptime as u128 * weight as u128 / warmup as u128) as usize;
+    ramped.clamp(1, weight)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_warmup_weight() {
+        assert_eq!(warmup_weight(100, 0, 60_000), 1);
+        assert_eq!(warmup_weight(100, 30_000, 60_000), 50);
+        assert_eq!(warmup_weight(100, 60_000, 60_000), 100);
+        assert_eq!(warmup_weight(100, 90_000, 0), 100);
+    }
+}
@@ -0,0 +1,350 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use dubbo_base::{
+    constants::{
+        OUTLIER_BASE_EJECTION_TIME_KEY, OUTLIER_CONSECUTIVE_FAILURES_KEY,
+        OUTLIER_FAILURE_PERCENT_KEY, OUTLIER_INTERVAL_KEY, OUTLIER_MAX_EJECTION_PERCENT_KEY,
+        OUTLIER_MAX_EJECTION_TIME_KEY, OUTLIER_MINIMUM_REQUESTS_KEY,
+    },
+    Url,
+};
+use dubbo_logger::tracing;
+
+use crate::{cluster::directory::Directory, invocation::RpcInvocation, status::Code};
+
+/// Thresholds of the outlier detection, read from the reference url.
+#[derive(Debug, Clone)]
+pub struct OutlierConfig {
+    pub consecutive_failures: u32,
+    // 0 disables the failure rate check
+    pub failure_percent: u64,
+    // the failure rate is only checked with enough requests in the interval
+    pub minimum_requests: u64,
+    pub interval: Duration,
+    pub base_ejection_time: Duration,
+    pub max_ejection_time: Duration,
+    pub max_ejection_percent: usize,
+}
+
+impl Default for OutlierConfig {
+    fn default() -> Self {
+        OutlierConfig {
+            consecutive_failures: 5,
+            failure_percent: 50,
+            minimum_requests: 20,
+            interval: Duration::from_secs(10),
+            base_ejection_time: Duration::from_secs(30),
+            max_ejection_time: Duration::from_secs(300),
+            max_ejection_percent: 10,
+        }
+    }
+}
+
+impl OutlierConfig {
+    pub fn from_url(url: &Url) -> Self {
+        fn param<T: FromStr>(url: &Url, key: &str, default: T) -> T {
+            url.get_param(key)
+                .and_then(|v| v.parse::<T>().ok())
+                .unwrap_or(default)
+        }
+        let default = OutlierConfig::default();
+        let millis = |key: &str, default: Duration| {
+            Duration::from_millis(param(url, key, default.as_millis() as u64))
+        };
+        OutlierConfig {
+            consecutive_failures: param(
+                url,
+                OUTLIER_CONSECUTIVE_FAILURES_KEY,
+                default.consecutive_failures,
+            ),
+            failure_percent: param(url, OUTLIER_FAILURE_PERCENT_KEY, default.failure_percent),
+            minimum_requests: param(url, OUTLIER_MINIMUM_REQUESTS_KEY, default.minimum_requests),
+            interval: millis(OUTLIER_INTERVAL_KEY, default.interval),
+            base_ejection_time: millis(OUTLIER_BASE_EJECTION_TIME_KEY, default.base_ejection_time),
+            max_ejection_time: millis(OUTLIER_MAX_EJECTION_TIME_KEY, default.max_ejection_time),
+            max_ejection_percent: param(
+                url,
+                OUTLIER_MAX_EJECTION_PERCENT_KEY,
+                default.max_ejection_percent,
+            ),
+        }
+    }
+}
+
+/// Passive health checking of the providers, as the outlier detection of envoy.
+///
+/// A provider is ejected after too many consecutive failures or a too high failure rate in an
+/// interval. The ejection time grows exponentially with the times a provider has been ejected
+/// recently, and at most `max_ejection_percent` of the listed providers are ejected at once,
+/// always leaving at least one provider.
+/// Only the failures caused by the provider count, not the errors returned by the service.
+#[derive(Debug)]
+pub struct OutlierDetector {
+    config: OutlierConfig,
+    hosts: Mutex<HashMap<String, HostState>>,
+}
+
+#[derive(Debug)]
+struct HostState {
+    consecutive_failures: u32,
+    requests: u64,
+    failures: u64,
+    interval_start: Instant,
+    ejected_at: Option<Instant>,
+    ejected_until: Option<Instant>,
+    // multiplier of the ejection time, decreased for each healthy interval
+    ejections: u32,
+}
+
+impl HostState {
+    fn new(now: Instant) -> Self {
+        HostState {
+            consecutive_failures: 0,
+            requests: 0,
+            failures: 0,
+            interval_start: now,
+            ejected_at: None,
+            ejected_until: None,
+            ejections: 0,
+        }
+    }
+
+    fn is_ejected(&self, now: Instant) -> bool {
+        matches!(self.ejected_until, Some(until) if until > now)
+    }
+}
+
+/// Whether `code` tells the provider is broken or overloaded, rather than the call being
+/// rejected by the application, eg. `NotFound`.
+pub(crate) fn is_provider_failure(code: Code) -> bool {
+    matches!(
+        code,
+        Code::Unavailable
+            | Code::DeadlineExceeded
+            | Code::Internal
+            | Code::Unknown
+            | Code::DataLoss
+    )
+}
+
+impl OutlierDetector {
+    pub fn new(config: OutlierConfig) -> Self {
+        OutlierDetector {
+            config,
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Records the result of a call to the provider at `address`.
+    pub fn record(&self, address: &str, code: Code) {
+        let now = Instant::now();
+        let mut hosts = self.hosts.lock().unwrap();
+        let host = hosts
+            .entry(address.to_string())
+            .or_insert_with(|| HostState::new(now));
+
+        if now.duration_since(host.interval_start) >= self.config.interval {
+            if !host.is_ejected(now) && host.failures == 0 {
+                host.ejections = host.ejections.saturating_sub(1);
+            }
+            host.requests = 0;
+            host.failures = 0;
+            host.interval_start = now;
+        }
+        if host.ejected_until.is_some_and(|until| until <= now) {
+            // back from ejection, start over
+            host.ejected_at = None;
+            host.ejected_until = None;
+            host.consecutive_failures = 0;
+        }
+
+        host.requests += 1;
+        if is_provider_failure(code) {
+            host.failures += 1;
+            host.consecutive_failures += 1;
+        } else {
+            host.consecutive_failures = 0;
+            return;
+        }
+        if host.is_ejected(now) {
+            return;
+        }
+
+        let config = &self.config;
+        let too_many_consecutive = config.consecutive_failures > 0
+            && host.consecutive_failures >= config.consecutive_failures;
+        let too_high_rate = config.failure_percent > 0
+            && host.requests >= config.minimum_requests
+            && host.failures * 100 >= host.requests * config.failure_percent;
+        if too_many_consecutive || too_high_rate {
+            let multiplier = 2u32.saturating_pow(host.ejections);
+            let ejection_time = config
+                .base_ejection_time
+                .saturating_mul(multiplier)
+                .min(config.max_ejection_time);
+            host.ejections = host.ejections.saturating_add(1);
+            host.ejected_at = Some(now);
+            host.ejected_until = Some(now + ejection_time);
+            tracing::warn!(
+                "eject provider {} for {:?}, consecutive failures: {}, failures: {}/{}",
+                address,
+                ejection_time,
+                host.consecutive_failures,
+                host.failures,
+                host.requests
+            );
+        }
+    }
+
+    /// Removes the ejected providers from `providers`, the earliest ejected ones first when more
+    /// than `max_ejection_percent` are ejected. At least one provider can be ejected, but never
+    /// the last one, so a single provider keeps its calls even when it is ejected.
+    ///
+    /// The state of the providers no longer listed is dropped.
+    pub fn filter(&self, providers: Vec<Url>) -> Vec<Url> {
+        let now = Instant::now();
+        let addresses: Vec<String> = providers.iter().map(|url| url.get_ip_port()).collect();
+        let mut hosts = self.hosts.lock().unwrap();
+        let listed: HashSet<&String> = addresses.iter().collect();
+        hosts.retain(|address, _| listed.contains(address));
+        let mut ejected: Vec<(Instant, &String)> = addresses
+            .iter()
+            .filter_map(|address| {
+                let host = hosts.get(address)?;
+                match host.ejected_at {
+                    Some(at) if host.is_ejected(now) => Some((at, address)),
+                    _ => None,
+                }
+            })
+            .collect();
+        drop(hosts);
+        let max_ejected = (providers.len() * self.config.max_ejection_percent / 100)
+            .max(1)
+            .min(providers.len().saturating_sub(1));
+        if ejected.is_empty() || max_ejected == 0 {
+            return providers;
+        }
+        ejected.sort();
+        ejected.truncate(max_ejected);
+        providers
+            .into_iter()
+            .zip(addresses.iter())
+            .filter(|(_, address)| !ejected.iter().any(|(_, ejected)| ejected == address))
+            .map(|(url, _)| url)
+            .collect()
+    }
+}
+
+/// Lists the providers of the inner directory except the ones ejected by the detector.
+#[derive(Debug, Clone)]
+pub struct OutlierDirectory {
+    inner: Arc<Box<dyn Directory>>,
+    detector: Arc<OutlierDetector>,
+}
+
+impl OutlierDirectory {
+    pub fn new(inner: Arc<Box<dyn Directory>>, detector: Arc<OutlierDetector>) -> Self {
+        OutlierDirectory { inner, detector }
+    }
+}
+
+impl Directory for OutlierDirectory {
+    fn list(&self, invocation: Arc<RpcInvocation>) -> Vec<Url> {
+        self.detector.filter(self.inner.list(invocation))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn providers() -> Vec<Url> {
+        (8001..=8004)
+            .map(|port| Url::from_url(&format!("tri://127.0.0.1:{}/Greeter", port)).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_eject_consecutive_failures() {
+        let detector = OutlierDetector::new(OutlierConfig {
+            consecutive_failures: 3,
+            max_ejection_percent: 50,
+            ..OutlierConfig::default()
+        });
+        detector.record("127.0.0.1:8001", Code::Unavailable);
+        detector.record("127.0.0.1:8001", Code::Unavailable);
+        // service errors do not count
+        detector.record("127.0.0.1:8001", Code::InvalidArgument);
+        detector.record("127.0.0.1:8001", Code::Unavailable);
+        detector.record("127.0.0.1:8001", Code::Unavailable);
+        assert_eq!(detector.filter(providers()).len(), 4);
+
+        detector.record("127.0.0.1:8001", Code::Unavailable);
+        let listed = detector.filter(providers());
+        assert_eq!(listed.len(), 3);
+        assert!(listed.iter().all(|url| url.port != "8001"));
+    }
+
+    #[test]
+    fn test_max_ejection_percent() {
+        let detector = OutlierDetector::new(OutlierConfig {
+            consecutive_failures: 1,
+            max_ejection_percent: 50,
+            ..OutlierConfig::default()
+        });
+        for port in 8001..=8004 {
+            detector.record(&format!("127.0.0.1:{}", port), Code::DeadlineExceeded);
+        }
+        assert_eq!(detector.filter(providers()).len(), 2);
+    }
+
+    #[test]
+    fn test_keep_last_provider() {
+        let detector = OutlierDetector::new(OutlierConfig {
+            consecutive_failures: 1,
+            ..OutlierConfig::default()
+        });
+        detector.record("127.0.0.1:8001", Code::Unavailable);
+        let single = vec![providers().remove(0)];
+        assert_eq!(detector.filter(single).len(), 1);
+
+        detector.record("127.0.0.1:8002", Code::Unavailable);
+        let listed = detector.filter(providers()[..2].to_vec());
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].port, "8002");
+    }
+
+    #[test]
+    fn test_forget_unlisted_providers() {
+        let detector = OutlierDetector::new(OutlierConfig::default());
+        for port in 8001..=8004 {
+            detector.record(&format!("127.0.0.1:{}", port), Code::Ok);
+        }
+        detector.filter(providers()[2..].to_vec());
+        let mut hosts: Vec<String> = detector.hosts.lock().unwrap().keys().cloned().collect();
+        hosts.sort();
+        assert_eq!(hosts, vec!["127.0.0.1:8003", "127.0.0.1:8004"]);
+    }
+}
@@ -0,0 +1,293 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::{
+    fmt::{Debug, Formatter},
+    sync::Arc,
+};
+
+use dubbo_base::Url;
+use dubbo_logger::tracing;
+
+use crate::{
+    cluster::router::{local_host, Router},
+    invocation::{Invocation, RpcInvocation},
+};
+
+/// Condition router, routes by rules of the form `<when> => <then>`, eg.
+/// `method = get*,find* & host = 10.0.0.* => host = 10.0.1.*`.
+///
+/// The `when` conditions match the consumer side: `method`, `service`, `host` (the consumer
+/// host), `attachments[key]` (the request metadata) and any other key of the reference url
+/// params. The `then` conditions match the provider url: `host`, `port`, `protocol`,
+/// `service` and the provider url params.
+///
+/// A condition is `key = v1,v2` or `key != v1,v2`, values may contain `*` wildcards, and
+/// conditions are joined by `&`. An empty `when` matches every call, an empty `then` rejects
+/// every provider. When no provider matches `then`, all providers are kept unless `force`.
+pub struct ConditionRouter {
+    rule: String,
+    when: Vec<Condition>,
+    // none rejects every provider
+    then: Option<Vec<Condition>>,
+    force: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Condition {
+    key: String,
+    negative: bool,
+    values: Vec<String>,
+}
+
+impl Condition {
+    /// Matches the value of the key, a missing key only matches a negative condition.
+    fn matches(&self, value: Option<&str>) -> bool {
+        match value {
+            Some(value) => {
+                let found = self.values.iter().any(|pattern| glob_match(pattern, value));
+                found != self.negative
+            }
+            None => self.negative,
+        }
+    }
+}
+
+impl ConditionRouter {
+    pub fn parse(rule: &str) -> Result<Self, crate::StdError> {
+        let (when, then) = match rule.find("=>") {
+            Some(index) => (&rule[..index], Some(&rule[index + 2..])),
+            None => (rule, None),
+        };
+        let when = parse_conditions(when)?;
+        let then = match then {
+            Some(then) if !then.trim().is_empty() => Some(parse_conditions(then)?),
+            _ => None,
+        };
+        Ok(ConditionRouter {
+            rule: rule.trim().to_string(),
+            when,
+            then,
+            force: false,
+        })
+    }
+
+    /// A forced rule returns no provider when no provider matches `then`.
+    pub fn with_force(self, force: bool) -> Self {
+        ConditionRouter { force, ..self }
+    }
+
+    fn match_when(&self, url: &Url, invocation: &RpcInvocation) -> bool {
+        self.when.iter().all(|condition| {
+            let value = match condition.key.as_str() {
+                "method" => Some(invocation.get_method_name()),
+                "service" => Some(invocation.get_target_service_unique_name()),
+                "host" => local_host().map(|v| v.to_string()),
+                key => match attachment_key(key) {
+                    Some(name) => invocation.get_attachment(name).map(|v| v.to_string()),
+                    None => url.get_param(key),
+                },
+            };
+            condition.matches(value.as_deref())
+        })
+    }
+
+    fn match_then(then: &[Condition], provider: &Url) -> bool {
+        then.iter().all(|condition| {
+            let value = match condition.key.as_str() {
+                "host" => Some(provider.ip.clone()),
+                "port" => Some(provider.port.clone()),
+                "protocol" => Some(provider.scheme.clone()),
+                "service" => Some(provider.service_name.clone()),
+                key => provider.get_param(key),
+            };
+            condition.matches(value.as_deref())
+        })
+    }
+}
+
+impl Debug for ConditionRouter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ConditionRouter({})", self.rule)
+    }
+}
+
+impl Router for ConditionRouter {
+    fn route(&self, invokers: Vec<Url>, url: &Url, invocation: Arc<RpcInvocation>) -> Vec<Url> {
+        if !self.match_when(url, &invocation) {
+            return invokers;
+        }
+        let then = match self.then.as_ref() {
+            Some(v) => v,
+            None => return Vec::new(),
+        };
+        let routed: Vec<Url> = invokers
+            .iter()
+            .filter(|provider| Self::match_then(then, provider))
+            .cloned()
+            .collect();
+        if !routed.is_empty() || self.force {
+            return routed;
+        }
+        tracing::warn!(
+            "no provider of {} matches route rule {}, ignore the rule",
+            invocation.unique_fingerprint(),
+            self.rule
+        );
+        invokers
+    }
+}
+
+// `attachments[key]` names the request metadata `key`
+fn attachment_key(key: &str) -> Option<&str> {
+    key.strip_prefix("attachments[")?.strip_suffix(']')
+}
+
+fn parse_conditions(expr: &str) -> Result<Vec<Condition>, crate::StdError> {
+    let mut conditions = Vec::new();
+    for part in expr.split('&') {
+        let part = part.trim();
+        if part.is_empty() {
+            continue;
+        }
+        let (key, negative, values) = match part.find("!=") {
+            Some(index) => (&part[..index], true, &part[index + 2..]),
+            None => match part.find('=') {
+                Some(index) => (&part[..index], false, &part[index + 1..]),
+                None => return Err(format!("illegal route condition `{}`", part).into()),
+            },
+        };
+        let key = key.trim();
+        let values: Vec<String> = values
+            .split(',')
+            .map(|v| v.trim())
+            .filter(|v| !v.is_empty())
+            .map(|v| v.to_string())
+            .collect();
+        if key.is_empty() || values.is_empty() {
+            return Err(format!("illegal route condition `{}`", part).into());
+        }
+        conditions.push(Condition {
+            key: key.to_string(),
+            negative,
+            values,
+        });
+    }
+    Ok(conditions)
+}
+
+/// Matches `value` against `pattern`, where `*` matches any characters.
+pub(crate) fn glob_match(pattern: &str, value: &str) -> bool {
+    if !pattern.contains('*') {
+        return pattern == value;
+    }
+    let parts: Vec<&str> = pattern.split('*').collect();
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if value.len() < first.len() + last.len() || !value.starts_with(first) || !value.ends_with(last)
+    {
+        return false;
+    }
+    let mut rest = &value[first.len()..value.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::invocation::Metadata;
+
+    fn providers() -> Vec<Url> {
+        vec![
+            Url::from_url("tri://10.0.0.1:8888/Greeter?version=1.0").unwrap(),
+            Url::from_url("tri://10.0.1.1:8888/Greeter?version=2.0").unwrap(),
+            Url::from_url("tri://10.0.1.2:8888/Greeter?version=2.0").unwrap(),
+        ]
+    }
+
+    fn invocation(method: &str) -> Arc<RpcInvocation> {
+        let mut headers = http::HeaderMap::new();
+        headers.insert("tenant", "vip".parse().unwrap());
+        Arc::new(
+            RpcInvocation::default()
+                .with_service_unique_name("Greeter".to_string())
+                .with_method_name(method.to_string())
+                .with_metadata(&Metadata::from_headers(headers)),
+        )
+    }
+
+    fn route(rule: &str, method: &str) -> Vec<String> {
+        ConditionRouter::parse(rule)
+            .unwrap()
+            .route(providers(), &Url::new(), invocation(method))
+            .iter()
+            .map(|url| url.ip.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("get*", "getUser"));
+        assert!(glob_match("*User", "getUser"));
+        assert!(glob_match("10.0.*.1", "10.0.1.1"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("get*", "findUser"));
+        assert!(!glob_match("a*a", "a"));
+        assert!(!glob_match("10.0.*.1", "10.0.1.2"));
+    }
+
+    #[test]
+    fn test_parse_rule() {
+        let router = ConditionRouter::parse("method = get*,find* & host != 10.0.0.1 => ").unwrap();
+        assert_eq!(router.when.len(), 2);
+        assert_eq!(router.when[0].values, vec!["get*", "find*"]);
+        assert!(router.when[1].negative);
+        assert!(router.then.is_none());
+
+        assert!(ConditionRouter::parse("method get => host = 1.1.1.1").is_err());
+        assert!(ConditionRouter::parse("method = => host = 1.1.1.1").is_err());
+    }
+
+    #[test]
+    fn test_route_by_method() {
+        let rule = "method = get* => host = 10.0.1.*";
+        assert_eq!(route(rule, "getUser"), vec!["10.0.1.1", "10.0.1.2"]);
+        assert_eq!(route(rule, "setUser").len(), 3);
+    }
+
+    #[test]
+    fn test_route_by_attachment_and_param() {
+        let rule = "attachments[tenant] = vip => version = 2.0 & host != 10.0.1.2";
+        assert_eq!(route(rule, "getUser"), vec!["10.0.1.1"]);
+    }
+
+    #[test]
+    fn test_route_blacklist_and_force() {
+        assert!(route("method = getUser => ", "getUser").is_empty());
+        assert_eq!(route("=> host = 10.0.2.*", "getUser").len(), 3);
+        let forced = ConditionRouter::parse("=> host = 10.0.2.*")
+            .unwrap()
+            .with_force(true)
+            .route(providers(), &Url::new(), invocation("getUser"));
+        assert!(forced.is_empty());
+    }
+}
@@ -0,0 +1,83 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::{fmt::Debug, sync::Arc};
+
+use dubbo_base::Url;
+use dubbo_utils::host_util;
+use lazy_static::lazy_static;
+
+use crate::invocation::RpcInvocation;
+
+pub mod condition;
+pub mod tag;
+pub mod traffic;
+pub mod zone;
+
+/// Router picks the providers eligible for an invocation, it runs between `Directory::list`
+/// and `LoadBalance::select`.
+pub trait Router: Debug + Send + Sync {
+    /// Filters `invokers` for `invocation`, `url` is the reference url of the consumer.
+    fn route(&self, invokers: Vec<Url>, url: &Url, invocation: Arc<RpcInvocation>) -> Vec<Url>;
+}
+
+pub type BoxRouter = Arc<dyn Router>;
+
+/// The routers of a reference, applied in order.
+#[derive(Debug, Clone, Default)]
+pub struct RouterChain {
+    routers: Vec<BoxRouter>,
+}
+
+impl RouterChain {
+    pub fn new() -> Self {
+        RouterChain::default()
+    }
+
+    pub fn with_router(mut self, router: BoxRouter) -> Self {
+        self.routers.push(router);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.routers.is_empty()
+    }
+
+    pub fn route(
+        &self,
+        mut invokers: Vec<Url>,
+        url: &Url,
+        invocation: Arc<RpcInvocation>,
+    ) -> Vec<Url> {
+        for router in self.routers.iter() {
+            if invokers.is_empty() {
+                break;
+            }
+            invokers = router.route(invokers, url, invocation.clone());
+        }
+        invokers
+    }
+}
+
+lazy_static! {
+    static ref LOCAL_HOST: Option<String> = host_util::local_ip().map(|ip| ip.to_string());
+}
+
+/// The ip of this host used to reach others, the `host` of the consumer in routing rules.
+pub fn local_host() -> Option<&'static str> {
+    LOCAL_HOST.as_deref()
+}
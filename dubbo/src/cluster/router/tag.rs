@@ -0,0 +1,125 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::Arc;
+
+use dubbo_base::{
+    constants::{FORCE_TAG_KEY, TAG_KEY},
+    Url,
+};
+
+use crate::{cluster::router::Router, invocation::RpcInvocation};
+
+/// Tag router, routes a request to the providers registered with its `dubbo.tag`.
+///
+/// The tag of a request comes from its `dubbo.tag` metadata, or else from the `dubbo.tag`
+/// param of the reference. A tagged request goes to the providers of the same tag, and
+/// to the untagged providers when none has the tag, unless `dubbo.force.tag` is `true`.
+/// A request without tag only goes to the untagged providers.
+#[derive(Debug, Default)]
+pub struct TagRouter {}
+
+impl TagRouter {
+    pub fn new() -> Self {
+        TagRouter {}
+    }
+}
+
+fn request_value(key: &str, url: &Url, invocation: &RpcInvocation) -> Option<String> {
+    invocation
+        .get_attachment(key)
+        .map(|v| v.to_string())
+        .or_else(|| url.get_param(key))
+        .filter(|v| !v.is_empty())
+}
+
+fn provider_tag(provider: &Url) -> Option<String> {
+    provider.get_param(TAG_KEY).filter(|v| !v.is_empty())
+}
+
+impl Router for TagRouter {
+    fn route(&self, invokers: Vec<Url>, url: &Url, invocation: Arc<RpcInvocation>) -> Vec<Url> {
+        let tag = match request_value(TAG_KEY, url, &invocation) {
+            Some(tag) => tag,
+            None => {
+                return invokers
+                    .into_iter()
+                    .filter(|provider| provider_tag(provider).is_none())
+                    .collect()
+            }
+        };
+        let (tagged, others): (Vec<Url>, Vec<Url>) = invokers
+            .into_iter()
+            .partition(|provider| provider_tag(provider).as_deref() == Some(tag.as_str()));
+        if !tagged.is_empty() {
+            return tagged;
+        }
+        let force = request_value(FORCE_TAG_KEY, url, &invocation)
+            .map(|v| v.eq_ignore_ascii_case("true"))
+            .unwrap_or(false);
+        if force {
+            return Vec::new();
+        }
+        others
+            .into_iter()
+            .filter(|provider| provider_tag(provider).is_none())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::invocation::Metadata;
+
+    fn providers() -> Vec<Url> {
+        vec![
+            Url::from_url("tri://10.0.0.1:8888/Greeter").unwrap(),
+            Url::from_url("tri://10.0.0.2:8888/Greeter?dubbo.tag=gray").unwrap(),
+            Url::from_url("tri://10.0.0.3:8888/Greeter?dubbo.tag=canary").unwrap(),
+        ]
+    }
+
+    fn route(attachments: &[(&str, &str)], url: &Url) -> Vec<String> {
+        let mut metadata = Metadata::new();
+        for (k, v) in attachments {
+            metadata.insert(k.to_string(), v.to_string());
+        }
+        let invocation = Arc::new(RpcInvocation::default().with_metadata(&metadata));
+        TagRouter::new()
+            .route(providers(), url, invocation)
+            .iter()
+            .map(|u| u.ip.clone())
+            .collect()
+    }
+
+    #[test]
+    fn route_by_tag() {
+        let url = Url::new();
+        assert_eq!(route(&[], &url), vec!["10.0.0.1"]);
+        assert_eq!(route(&[(TAG_KEY, "gray")], &url), vec!["10.0.0.2"]);
+        // no provider of the tag, fallback to untagged ones
+        assert_eq!(route(&[(TAG_KEY, "blue")], &url), vec!["10.0.0.1"]);
+        assert!(route(&[(TAG_KEY, "blue"), (FORCE_TAG_KEY, "true")], &url).is_empty());
+
+        let mut url = Url::new();
+        url.set_param(TAG_KEY, "canary");
+        assert_eq!(route(&[], &url), vec!["10.0.0.3"]);
+        // the request metadata overrides the reference
+        assert_eq!(route(&[(TAG_KEY, "gray")], &url), vec!["10.0.0.2"]);
+    }
+}
@@ -0,0 +1,306 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::{
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use dubbo_base::Url;
+use dubbo_config::router::TrafficConfig;
+use dubbo_logger::tracing;
+use rand::Rng;
+
+use crate::{cluster::router::Router, invocation::RpcInvocation, utils::watch::watch_files};
+
+/// How often a traffic file of a reference is checked for changes.
+pub const WATCH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Traffic router, splits the calls between subsets of the providers by weight, see
+/// [`TrafficConfig`]. The calls go to the providers of the subset picked by the first route
+/// matching the request metadata, the subsets without provider are left out of the split.
+/// When no route matches or no subset has a provider, every provider is kept.
+///
+/// The rule is replaced by [`TrafficRouter::update`] or by watching a rule file, the calls
+/// in flight keep the rule they started with.
+#[derive(Debug)]
+pub struct TrafficRouter {
+    rule: RwLock<Arc<TrafficRule>>,
+}
+
+#[derive(Debug)]
+struct TrafficRule {
+    // url params of the providers of each subset
+    subsets: Vec<Vec<(String, String)>>,
+    routes: Vec<Route>,
+}
+
+#[derive(Debug)]
+struct Route {
+    // header names are lowercase, as in the request metadata
+    headers: Vec<(String, String)>,
+    // index of the subset and its weight
+    split: Vec<(usize, u32)>,
+}
+
+impl TrafficRule {
+    fn parse(config: &TrafficConfig) -> Result<Self, crate::StdError> {
+        let mut names = Vec::with_capacity(config.subsets.len());
+        let mut subsets = Vec::with_capacity(config.subsets.len());
+        for (name, params) in config.subsets.iter() {
+            names.push(name.as_str());
+            subsets.push(
+                params
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect::<Vec<_>>(),
+            );
+        }
+        let mut routes = Vec::with_capacity(config.routes.len());
+        for route in config.routes.iter() {
+            let mut split = Vec::with_capacity(route.split.len());
+            for weight in route.split.iter() {
+                let index = names
+                    .iter()
+                    .position(|name| *name == weight.subset)
+                    .ok_or_else(|| format!("traffic subset {} is not defined", weight.subset))?;
+                split.push((index, weight.weight));
+            }
+            if split.iter().all(|(_, weight)| *weight == 0) {
+                return Err("traffic route has no weighted subset".into());
+            }
+            let headers = route
+                .headers
+                .iter()
+                .map(|(k, v)| (k.to_ascii_lowercase(), v.clone()))
+                .collect();
+            routes.push(Route { headers, split });
+        }
+        Ok(TrafficRule { subsets, routes })
+    }
+
+    fn contains(&self, subset: usize, provider: &Url) -> bool {
+        self.subsets[subset]
+            .iter()
+            .all(|(k, v)| provider.get_param(k).as_deref() == Some(v.as_str()))
+    }
+}
+
+impl TrafficRouter {
+    pub fn new(config: &TrafficConfig) -> Result<Self, crate::StdError> {
+        Ok(TrafficRouter {
+            rule: RwLock::new(Arc::new(TrafficRule::parse(config)?)),
+        })
+    }
+
+    /// Replaces the rule, the current rule is kept when `config` is invalid.
+    pub fn update(&self, config: &TrafficConfig) -> Result<(), crate::StdError> {
+        let rule = Arc::new(TrafficRule::parse(config)?);
+        *self.rule.write().unwrap() = rule;
+        Ok(())
+    }
+
+    /// Reloads the rule from the yaml file `path` every time it is modified, checking it every
+    /// `interval`, until the router is dropped. Needs a tokio runtime.
+    pub fn watch(self: &Arc<Self>, path: PathBuf, interval: Duration) {
+        let files = vec![path.clone()];
+        watch_files(self, files, interval, move |router: &Self| {
+            let result = TrafficConfig::load(&path)
+                .map_err(crate::StdError::from)
+                .and_then(|config| router.update(&config));
+            match result.as_ref() {
+                Ok(_) => tracing::info!("traffic rule reloaded from {:?}", path),
+                Err(err) => {
+                    tracing::warn!("invalid traffic rule {:?}, keep the current: {}", path, err)
+                }
+            }
+            result
+        });
+    }
+}
+
+impl Router for TrafficRouter {
+    fn route(&self, invokers: Vec<Url>, _url: &Url, invocation: Arc<RpcInvocation>) -> Vec<Url> {
+        let rule = self.rule.read().unwrap().clone();
+        let route = rule.routes.iter().find(|route| {
+            route
+                .headers
+                .iter()
+                .all(|(k, v)| invocation.get_attachment(k) == Some(v.as_str()))
+        });
+        let route = match route {
+            Some(v) => v,
+            None => return invokers,
+        };
+        let candidates: Vec<(usize, u32)> = route
+            .split
+            .iter()
+            .filter(|(subset, weight)| {
+                *weight > 0 && invokers.iter().any(|url| rule.contains(*subset, url))
+            })
+            .cloned()
+            .collect();
+        let total: u32 = candidates.iter().map(|(_, weight)| weight).sum();
+        if total == 0 {
+            return invokers;
+        }
+        let mut offset = rand::thread_rng().gen_range(0..total);
+        let mut picked = candidates[0].0;
+        for (subset, weight) in candidates {
+            if offset < weight {
+                picked = subset;
+                break;
+            }
+            offset -= weight;
+        }
+        invokers
+            .into_iter()
+            .filter(|url| rule.contains(picked, url))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use std::time::SystemTime;
+
+    use super::*;
+    use crate::invocation::Metadata;
+    use dubbo_config::router::{SubsetWeight, TrafficRoute};
+
+    fn providers() -> Vec<Url> {
+        vec![
+            Url::from_url("tri://10.0.0.1:8888/Greeter?version=1.0").unwrap(),
+            Url::from_url("tri://10.0.0.2:8888/Greeter?version=2.0").unwrap(),
+        ]
+    }
+
+    fn config(subsets: &[(&str, &str)], routes: Vec<TrafficRoute>) -> TrafficConfig {
+        TrafficConfig {
+            subsets: subsets
+                .iter()
+                .map(|(name, version)| {
+                    let params = HashMap::from([("version".to_string(), version.to_string())]);
+                    (name.to_string(), params)
+                })
+                .collect(),
+            routes,
+        }
+    }
+
+    fn split(headers: &[(&str, &str)], split: &[(&str, u32)]) -> TrafficRoute {
+        TrafficRoute {
+            headers: headers
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            split: split
+                .iter()
+                .map(|(subset, weight)| SubsetWeight {
+                    subset: subset.to_string(),
+                    weight: *weight,
+                })
+                .collect(),
+        }
+    }
+
+    fn route(router: &TrafficRouter, headers: &[(&str, &str)]) -> Vec<String> {
+        let mut metadata = Metadata::new();
+        for (k, v) in headers {
+            metadata.insert(k.to_string(), v.to_string());
+        }
+        let invocation = Arc::new(RpcInvocation::default().with_metadata(&metadata));
+        router
+            .route(providers(), &Url::new(), invocation)
+            .iter()
+            .map(|u| u.ip.clone())
+            .collect()
+    }
+
+    #[test]
+    fn split_by_route() {
+        let subsets = [("v1", "1.0"), ("v2", "2.0"), ("v3", "3.0")];
+        let routes = vec![
+            split(&[("User-Type", "beta")], &[("v2", 100)]),
+            split(&[], &[("v1", 100), ("v3", 900)]),
+        ];
+        let router = TrafficRouter::new(&config(&subsets, routes)).unwrap();
+        assert_eq!(route(&router, &[("user-type", "beta")]), vec!["10.0.0.2"]);
+        // v3 has no provider, v1 takes all of the calls
+        assert_eq!(route(&router, &[]), vec!["10.0.0.1"]);
+
+        router.update(&config(&subsets, Vec::new())).unwrap();
+        assert_eq!(route(&router, &[]).len(), 2);
+
+        let invalid = config(&subsets, vec![split(&[], &[("v4", 1)])]);
+        assert!(router.update(&invalid).is_err());
+        assert_eq!(route(&router, &[]).len(), 2);
+    }
+
+    #[test]
+    fn split_by_weight() {
+        let subsets = [("v1", "1.0"), ("v2", "2.0")];
+        let routes = vec![split(&[], &[("v1", 95), ("v2", 5)])];
+        let router = TrafficRouter::new(&config(&subsets, routes)).unwrap();
+        let v2 = (0..10000)
+            .filter(|_| route(&router, &[]) == vec!["10.0.0.2"])
+            .count();
+        // 500 expected, the standard deviation is about 22
+        assert!((400..600).contains(&v2), "v2 got {} calls", v2);
+    }
+
+    #[tokio::test]
+    async fn reload_rule_file() {
+        let path = std::env::temp_dir().join(format!("dubbo-traffic-{}.yaml", std::process::id()));
+        let rule = |subset: &str| {
+            format!(
+                "subsets:\n  v1:\n    version: '1.0'\n  v2:\n    version: '2.0'\n\
+                 routes:\n  - split:\n      - subset: {}\n        weight: 1\n",
+                subset
+            )
+        };
+        let write = |content: &str, modified: SystemTime| {
+            std::fs::write(&path, content).unwrap();
+            let file = std::fs::File::options().write(true).open(&path).unwrap();
+            file.set_modified(modified).unwrap();
+        };
+        std::fs::write(&path, rule("v1")).unwrap();
+        let router = Arc::new(TrafficRouter::new(&TrafficConfig::load(&path).unwrap()).unwrap());
+        router.watch(path.clone(), Duration::from_millis(20));
+        // the watch starts from the current modification time
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(route(&router, &[]), vec!["10.0.0.1"]);
+
+        // a rule being written is invalid for a moment, and the complete one may keep its
+        // modification time
+        let modified = SystemTime::now() + Duration::from_secs(10);
+        write("subsets: [", modified);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(route(&router, &[]), vec!["10.0.0.1"]);
+        write(&rule("v2"), modified);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(route(&router, &[]), vec!["10.0.0.2"]);
+
+        write(&rule("v1"), modified + Duration::from_secs(10));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(route(&router, &[]), vec!["10.0.0.1"]);
+    }
+}
@@ -0,0 +1,123 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::Arc;
+
+use dubbo_base::{
+    constants::{REGION_KEY, ZONE_KEY, ZONE_MIN_HEALTHY_KEY},
+    Url,
+};
+
+use crate::{cluster::router::Router, invocation::RpcInvocation};
+
+/// The number of providers in the zone below which the calls go out of the zone.
+pub const DEFAULT_MIN_HEALTHY: usize = 1;
+
+/// Zone aware router, keeps the calls in the `zone` and then the `region` of the consumer,
+/// which are the params of the reference url.
+///
+/// The providers reach the router after the ejected and unhealthy ones are removed, the
+/// calls go to the providers of the region once fewer than `zone.min.healthy` providers
+/// are left in the zone, and to every provider once the region is short as well.
+#[derive(Debug, Default)]
+pub struct ZoneRouter {}
+
+impl ZoneRouter {
+    pub fn new() -> Self {
+        ZoneRouter {}
+    }
+}
+
+fn local(invokers: &[Url], key: &str, value: &str) -> Vec<Url> {
+    invokers
+        .iter()
+        .filter(|url| url.get_param(key).as_deref() == Some(value))
+        .cloned()
+        .collect()
+}
+
+impl Router for ZoneRouter {
+    fn route(&self, invokers: Vec<Url>, url: &Url, _invocation: Arc<RpcInvocation>) -> Vec<Url> {
+        let min_healthy = url
+            .get_param(ZONE_MIN_HEALTHY_KEY)
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(DEFAULT_MIN_HEALTHY)
+            .max(1);
+        for key in [ZONE_KEY, REGION_KEY] {
+            let value = match url.get_param(key) {
+                Some(v) if !v.is_empty() => v,
+                _ => continue,
+            };
+            let local = local(&invokers, key, &value);
+            if local.len() >= min_healthy {
+                return local;
+            }
+        }
+        invokers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn providers() -> Vec<Url> {
+        vec![
+            Url::from_url("tri://10.0.0.1:8888/Greeter?zone=a1&region=a").unwrap(),
+            Url::from_url("tri://10.0.0.2:8888/Greeter?zone=a2&region=a").unwrap(),
+            Url::from_url("tri://10.0.0.3:8888/Greeter?zone=a2&region=a").unwrap(),
+            Url::from_url("tri://10.0.0.4:8888/Greeter?zone=b1&region=b").unwrap(),
+        ]
+    }
+
+    fn route(params: &[(&str, &str)]) -> Vec<String> {
+        let mut url = Url::new();
+        for (k, v) in params {
+            url.set_param(k, v);
+        }
+        ZoneRouter::new()
+            .route(providers(), &url, Arc::new(RpcInvocation::default()))
+            .iter()
+            .map(|u| u.ip.clone())
+            .collect()
+    }
+
+    #[test]
+    fn prefer_local_zone() {
+        assert_eq!(route(&[]).len(), 4);
+        assert_eq!(
+            route(&[(ZONE_KEY, "a1"), (REGION_KEY, "a")]),
+            vec!["10.0.0.1"]
+        );
+        // the zone is short, fall over to the region
+        let region = route(&[
+            (ZONE_KEY, "a1"),
+            (REGION_KEY, "a"),
+            (ZONE_MIN_HEALTHY_KEY, "2"),
+        ]);
+        assert_eq!(region, vec!["10.0.0.1", "10.0.0.2", "10.0.0.3"]);
+        // the region is short as well
+        let all = route(&[
+            (ZONE_KEY, "b1"),
+            (REGION_KEY, "b"),
+            (ZONE_MIN_HEALTHY_KEY, "2"),
+        ]);
+        assert_eq!(all.len(), 4);
+        // unknown zone
+        assert_eq!(route(&[(ZONE_KEY, "c1")]).len(), 4);
+    }
+}
@@ -0,0 +1,281 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use aws_smithy_http::body::SdkBody;
+use bytes::{BufMut, Bytes, BytesMut};
+use dubbo_base::constants::BROADCAST_FAIL_PERCENT_KEY;
+use dubbo_logger::tracing;
+use futures_util::future::join_all;
+use http_body::Body;
+use tower_service::Service;
+
+use crate::{
+    cluster::support::{
+        clone_request, cluster_invoker::ClusterInvoker, get_invocation, invoke_provider,
+        no_provider_status, Failures,
+    },
+    invocation::Invocation,
+    status::{Code, Status},
+};
+
+/// Invoke all providers of the directory at the same time, usually used to notify all
+/// providers to update local resource information such as caches or configs.
+///
+/// The call fails if any provider fails, unless `broadcast.fail.percent` is set, then it only
+/// fails when the failed providers exceed that percentage. The response of the last successful
+/// provider is returned.
+///
+/// A provider fails by any status but OK, either in the headers or in the trailers of its
+/// response, which is read in full before the call returns.
+#[derive(Debug, Clone)]
+pub struct BroadcastCluster {
+    invoker: ClusterInvoker,
+}
+
+impl BroadcastCluster {
+    pub fn new(invoker: ClusterInvoker) -> BroadcastCluster {
+        Self { invoker }
+    }
+}
+
+impl Service<http::Request<SdkBody>> for BroadcastCluster {
+    type Response = http::Response<crate::BoxBody>;
+
+    type Error = crate::Error;
+
+    type Future = crate::BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(
+        &mut self,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<SdkBody>) -> Self::Future {
+        let invoker = self.invoker.clone();
+        Box::pin(async move {
+            let invocation = get_invocation(&req)?;
+            let fail_percent = invoker
+                .url()
+                .get_method_param(&invocation.get_method_name(), BROADCAST_FAIL_PERCENT_KEY)
+                .and_then(|v| v.parse::<usize>().ok())
+                .map(|v| v.min(100));
+
+            let providers = invoker.list(invocation.clone());
+            if providers.is_empty() {
+                return Err(no_provider_status(&invocation).into());
+            }
+
+            let mut calls = Vec::with_capacity(providers.len());
+            for provider in providers.iter() {
+                let attempt = match clone_request(&req) {
+                    Some(v) => v,
+                    None => {
+                        return Err(Status::new(
+                            Code::Unimplemented,
+                            "broadcast cluster does not support streaming request".to_string(),
+                        )
+                        .into())
+                    }
+                };
+                let invoker = invoker.clone();
+                let provider = provider.clone();
+                calls.push(async move {
+                    let res = match invoke_provider(&invoker, &provider, attempt, Failures::NotOk)
+                        .await
+                    {
+                        Ok(resp) => read_response(resp).await,
+                        Err(status) => Err(status),
+                    };
+                    (provider, res)
+                });
+            }
+
+            let total = calls.len();
+            let mut failed = 0;
+            let mut last_status = None;
+            let mut last_resp = None;
+            for (provider, res) in join_all(calls).await {
+                match res {
+                    Ok(resp) => last_resp = Some(resp),
+                    Err(status) => {
+                        tracing::warn!(
+                            "broadcast invoke {} on {} failed: {}",
+                            invocation.unique_fingerprint(),
+                            provider,
+                            status
+                        );
+                        failed += 1;
+                        last_status = Some(status);
+                    }
+                }
+            }
+
+            let exceeded = match fail_percent {
+                Some(percent) => failed * 100 > total * percent,
+                None => failed > 0,
+            };
+            match (last_resp, last_status) {
+                (Some(resp), _) if !exceeded => Ok(resp),
+                (_, Some(status)) => Err(Status::new(
+                    status.code(),
+                    format!(
+                        "broadcast invoke {} failed on {}/{} providers, last error: {}",
+                        invocation.unique_fingerprint(),
+                        failed,
+                        total,
+                        status.message()
+                    ),
+                )
+                .into()),
+                (_, None) => Err(no_provider_status(&invocation).into()),
+            }
+        })
+    }
+}
+
+/// Reads the whole response of a provider, which fails by the status of its trailers as well
+/// as by the one of a trailers-only response.
+async fn read_response(
+    resp: http::Response<crate::BoxBody>,
+) -> Result<http::Response<crate::BoxBody>, Status> {
+    let (parts, mut body) = resp.into_parts();
+    let mut data = BytesMut::new();
+    while let Some(chunk) = body.data().await {
+        data.put(chunk?);
+    }
+    let trailers = body.trailers().await?;
+    if let Some(status) = trailers.as_ref().and_then(Status::from_header_map) {
+        if Failures::NotOk.contains(status.code()) {
+            return Err(status);
+        }
+    }
+    let body = ReadBody {
+        data: Some(data.freeze()).filter(|data| !data.is_empty()),
+        trailers,
+    };
+    Ok(http::Response::from_parts(parts, body.boxed_unsync()))
+}
+
+/// The body of a response read in full.
+struct ReadBody {
+    data: Option<Bytes>,
+    trailers: Option<http::HeaderMap>,
+}
+
+impl Body for ReadBody {
+    type Data = Bytes;
+
+    type Error = Status;
+
+    fn poll_data(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        Poll::Ready(self.data.take().map(Ok))
+    }
+
+    fn poll_trailers(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<http::HeaderMap>, Self::Error>> {
+        Poll::Ready(Ok(self.trailers.take()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dubbo_base::Url;
+
+    use crate::cluster::support::test_util::{invoker, request, TestProvider};
+
+    use super::*;
+
+    /// Starts `ok` providers answering OK and `failed` ones failing.
+    async fn start(ok: usize, failed: usize) -> (Vec<TestProvider>, Vec<Url>) {
+        let mut providers = Vec::new();
+        for _ in 0..ok {
+            providers.push(TestProvider::start(Code::Ok).await);
+        }
+        for _ in 0..failed {
+            providers.push(TestProvider::start(Code::Internal).await);
+        }
+        let urls = providers.iter().map(|p| p.url.clone()).collect();
+        (providers, urls)
+    }
+
+    async fn broadcast(urls: Vec<Url>, fail_percent: &str) -> Result<(), Code> {
+        let invoker = invoker(urls, &[(BROADCAST_FAIL_PERCENT_KEY, fail_percent)]);
+        BroadcastCluster::new(invoker)
+            .call(request())
+            .await
+            .map(|_| ())
+            .map_err(|err| Status::from_error(err).code())
+    }
+
+    #[tokio::test]
+    async fn zero_percent_fails_on_any_failure() {
+        let (providers, urls) = start(3, 1).await;
+        assert_eq!(broadcast(urls, "0").await, Err(Code::Internal));
+        assert!(providers.iter().all(|p| p.calls() == 1));
+        let (_providers, urls) = start(2, 0).await;
+        assert_eq!(broadcast(urls, "0").await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn partial_percent() {
+        // 1 of 4 failed is 25%
+        let (_providers, urls) = start(3, 1).await;
+        assert_eq!(broadcast(urls.clone(), "25").await, Ok(()));
+        assert_eq!(broadcast(urls, "20").await, Err(Code::Internal));
+    }
+
+    #[tokio::test]
+    async fn hundred_percent_needs_one_success() {
+        let (_providers, urls) = start(1, 3).await;
+        assert_eq!(broadcast(urls, "100").await, Ok(()));
+        let (_providers, urls) = start(0, 2).await;
+        assert_eq!(broadcast(urls, "100").await, Err(Code::Internal));
+    }
+
+    #[tokio::test]
+    async fn trailers_status_fails_provider() {
+        let ok = TestProvider::start(Code::Ok).await;
+        let failed = TestProvider::start_with_trailers(Code::Internal).await;
+        let urls = vec![ok.url.clone(), failed.url.clone()];
+        assert_eq!(broadcast(urls.clone(), "0").await, Err(Code::Internal));
+        assert_eq!(broadcast(urls, "50").await, Ok(()));
+
+        // the read response is returned with its message and trailers
+        let streamed = TestProvider::start_with_trailers(Code::Ok).await;
+        let invoker = invoker(vec![streamed.url.clone()], &[]);
+        let mut resp = BroadcastCluster::new(invoker)
+            .call(request())
+            .await
+            .unwrap();
+        let data = resp.data().await.unwrap().unwrap();
+        assert_eq!(&data[..], &[0u8; 5]);
+        let trailers = resp.trailers().await.unwrap().unwrap();
+        assert_eq!(Status::from_header_map(&trailers).unwrap().code(), Code::Ok);
+    }
+}
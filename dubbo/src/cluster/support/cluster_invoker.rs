@@ -18,21 +18,47 @@
 use aws_smithy_http::body::SdkBody;
 use std::{str::FromStr, sync::Arc};
 
-use dubbo_base::Url;
-use http::{uri::PathAndQuery, Request};
+use dubbo_base::{
+    constants::{
+        CLUSTER_KEY, CONNECTIONS_KEY, HEALTH_CHECK_KEY, LOADBALANCE_KEY,
+        MAX_CONCURRENT_STREAMS_KEY, MOCK_KEY, OUTLIER_DETECTION_KEY,
+    },
+    Url,
+};
+use http::HeaderValue;
+use tower_service::Service;
 
 use crate::{
     cluster::{
+        health_check::{HealthCheckConfig, HealthCheckDirectory, HealthChecker},
         loadbalance::{types::BoxLoadBalance, LOAD_BALANCE_EXTENSIONS},
-        support::DEFAULT_LOADBALANCE,
+        outlier::{OutlierConfig, OutlierDetector, OutlierDirectory},
+        router::{BoxRouter, RouterChain},
+        support::{
+            check_url,
+            mock::{Fallback, MockCluster},
+            CLUSTER_EXTENSIONS, DEFAULT_CLUSTER, DEFAULT_LOADBALANCE,
+        },
+        ClusterBoxService,
+    },
+    codegen::{Directory, RegistryDirectory},
+    invocation::{Invocation, RpcInvocation},
+    triple::{
+        client::rpc_status,
+        transport::{connection::Connection, pool::ConnectionPool},
     },
-    codegen::{Directory, RegistryDirectory, TripleClient},
-    invocation::RpcInvocation,
 };
 
 #[derive(Debug, Clone)]
 pub struct ClusterInvoker {
-    directory: Arc<RegistryDirectory>,
+    directory: Arc<Box<dyn Directory>>,
+    // reference url, its params tune the cluster behaviour, eg. retries
+    url: Url,
+    outlier: Option<Arc<OutlierDetector>>,
+    router_chain: RouterChain,
+    fallback: Option<Fallback>,
+    // dedicated connections of the reference, the shared pool is used when unset
+    pool: Option<ConnectionPool>,
     destroyed: bool,
 }
 
@@ -53,47 +79,184 @@ pub trait ClusterInvokerSelector {
     ) -> Option<Url>;
 }
 
-pub trait ClusterRequestBuilder {
-    fn build_req(
-        &self,
-        triple_client: &mut TripleClient,
-        path: http::uri::PathAndQuery,
-        invocation: Arc<RpcInvocation>,
-        body: SdkBody,
-    ) -> http::Request<SdkBody>;
-}
-
 impl ClusterInvoker {
-    pub fn with_directory(registry_directory: RegistryDirectory) -> Self {
+    pub fn with_directory(directory: Box<dyn Directory>) -> Self {
         ClusterInvoker {
-            directory: Arc::new(registry_directory),
+            directory: Arc::new(directory),
+            url: Url::default(),
+            outlier: None,
+            router_chain: RouterChain::new(),
+            fallback: None,
+            pool: None,
             destroyed: false,
         }
     }
 
-    pub fn directory(&self) -> Arc<RegistryDirectory> {
+    pub fn with_registry_directory(registry_directory: RegistryDirectory) -> Self {
+        Self::with_directory(Box::new(registry_directory))
+    }
+
+    /// Sets the reference url. The providers are actively health checked when its
+    /// `health.check` param is true, and passively when its `outlier.detection` param is true.
+    /// The calls round-robin over `connections` connections to each provider when the param is
+    /// set, another connection is opened when all of them reach `max.concurrent.streams`.
+    /// The health checks go over the pool set by [`with_pool`](Self::with_pool) before.
+    ///
+    /// Fails when the url names an unknown cluster or loadbalance, or has an invalid mock or
+    /// connection count.
+    pub fn with_url(self, url: Url) -> Result<Self, crate::StdError> {
+        check_url(&url).map_err(|err| format!("invalid url of {}: {}", url.service_name, err))?;
+        Ok(self.with_checked_url(url))
+    }
+
+    /// Sets the reference url, which has passed [`check_url`].
+    pub(crate) fn with_checked_url(self, url: Url) -> Self {
+        let enabled = |key: &str| url.get_param(key).as_deref() == Some("true");
+        let count = |key: &str| url.get_param(key).and_then(|v| v.parse::<usize>().ok());
+        let mut pool = self.pool;
+        if let Some(connections) = count(CONNECTIONS_KEY) {
+            let mut dedicated = pool
+                .unwrap_or_else(|| ConnectionPool::new("http"))
+                .with_connections(connections);
+            if let Some(max_streams) = count(MAX_CONCURRENT_STREAMS_KEY) {
+                dedicated = dedicated.with_max_streams(max_streams);
+            }
+            pool = Some(dedicated);
+        }
+        let mut directory = self.directory;
+        if enabled(HEALTH_CHECK_KEY) {
+            let mut checker = HealthChecker::new(HealthCheckConfig::from_url(&url));
+            if let Some(pool) = pool.clone() {
+                checker = checker.with_pool(pool);
+            }
+            let checker = Arc::new(checker);
+            directory = Arc::new(Box::new(HealthCheckDirectory::new(directory, checker)));
+        }
+        let mut outlier = self.outlier;
+        if enabled(OUTLIER_DETECTION_KEY) && outlier.is_none() {
+            let detector = Arc::new(OutlierDetector::new(OutlierConfig::from_url(&url)));
+            directory = Arc::new(Box::new(OutlierDirectory::new(directory, detector.clone())));
+            outlier = Some(detector);
+        }
+        ClusterInvoker {
+            directory,
+            url,
+            outlier,
+            pool,
+            ..self
+        }
+    }
+
+    /// Sends the calls by `pool` instead of the shared pool, eg. to connect by TLS.
+    pub fn with_pool(self, pool: ConnectionPool) -> Self {
+        ClusterInvoker {
+            pool: Some(pool),
+            ..self
+        }
+    }
+
+    /// Appends a router, the routers run in the order they are added.
+    pub fn with_router(self, router: BoxRouter) -> Self {
+        ClusterInvoker {
+            router_chain: self.router_chain.with_router(router),
+            ..self
+        }
+    }
+
+    /// Degrades the failed calls by `fallback`, unless the `mock` param of the method says
+    /// otherwise.
+    pub fn with_fallback(self, fallback: Fallback) -> Self {
+        ClusterInvoker {
+            fallback: Some(fallback),
+            ..self
+        }
+    }
+
+    pub fn directory(&self) -> Arc<Box<dyn Directory>> {
         self.directory.clone()
     }
 
-    pub fn init_loadbalance(&self, loadbalance_key: &str) -> &BoxLoadBalance {
-        if LOAD_BALANCE_EXTENSIONS.contains_key(loadbalance_key) {
-            LOAD_BALANCE_EXTENSIONS.get(loadbalance_key).unwrap()
-        } else {
-            println!(
-                "loadbalance {} not found, use default loadbalance {}",
-                loadbalance_key, DEFAULT_LOADBALANCE
-            );
-            LOAD_BALANCE_EXTENSIONS.get(DEFAULT_LOADBALANCE).unwrap()
+    /// The providers of the directory eligible for `invocation` after routing.
+    pub fn list(&self, invocation: Arc<RpcInvocation>) -> Vec<Url> {
+        let invokers = self.directory.list(invocation.clone());
+        if self.router_chain.is_empty() {
+            return invokers;
+        }
+        self.router_chain.route(invokers, &self.url, invocation)
+    }
+
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// The loadbalance named `loadbalance_key`, `None` if it is not registered.
+    pub fn init_loadbalance(&self, loadbalance_key: &str) -> Option<&BoxLoadBalance> {
+        LOAD_BALANCE_EXTENSIONS.get(loadbalance_key)
+    }
+
+    /// Joins into the cluster strategy named by the `cluster` param, failover by default.
+    /// The cluster is wrapped by a [`MockCluster`] when a mock or fallback is configured.
+    pub fn join(self) -> ClusterBoxService {
+        let name = self
+            .url
+            .get_param(CLUSTER_KEY)
+            .unwrap_or_else(|| DEFAULT_CLUSTER.to_string());
+        let join = CLUSTER_EXTENSIONS[&name];
+        let mocked = self.fallback.is_some()
+            || self
+                .url
+                .params
+                .keys()
+                .any(|key| key == MOCK_KEY || key.ends_with(&format!(".{}", MOCK_KEY)));
+        if !mocked {
+            return join(self);
         }
+        let url = self.url.clone();
+        let fallback = self.fallback.clone();
+        ClusterBoxService::new(MockCluster::new(join(self), url, fallback))
     }
 
     pub fn is_available(&self, invocation: Arc<RpcInvocation>) -> bool {
-        !self.destroyed() && !self.directory.list(invocation).is_empty()
+        !self.destroyed() && !self.list(invocation).is_empty()
     }
 
     pub fn destroyed(&self) -> bool {
         self.destroyed
     }
+
+    /// Sends `req` to the provider of `url`, the scheme and authority of the request uri are
+    /// replaced by the provider address. The call is counted in the provider statistics until
+    /// its response ends.
+    pub fn invoke(
+        &self,
+        url: &Url,
+        req: http::Request<SdkBody>,
+    ) -> crate::BoxFuture<http::Response<crate::BoxBody>, crate::Error> {
+        let uri = match http::Uri::from_str(&format!("http://{}:{}/", url.ip, url.port)) {
+            Ok(v) => v,
+            Err(err) => return Box::pin(async move { Err(err.into()) }),
+        };
+        let req = match route_request(req, &uri) {
+            Ok(v) => v,
+            Err(err) => return Box::pin(async move { Err(err) }),
+        };
+        let method = req
+            .extensions()
+            .get::<Arc<RpcInvocation>>()
+            .map(|invocation| invocation.get_method_name());
+        let listener = self.outlier.clone().map(|detector| {
+            let address = url.get_ip_port();
+            Box::new(move |code| detector.record(&address, code)) as rpc_status::CallListener
+        });
+        let mut conn = Connection::new().with_host(uri);
+        if let Some(pool) = self.pool.clone() {
+            conn = conn.with_pool(pool);
+        }
+        match method {
+            Some(method) => rpc_status::track(url, &method, listener, conn.call(req)),
+            None => conn.call(req),
+        }
+    }
 }
 
 impl ClusterInvokerSelector for ClusterInvoker {
@@ -101,18 +264,35 @@ impl ClusterInvokerSelector for ClusterInvoker {
         &self,
         invocation: Arc<RpcInvocation>,
         invokers: Arc<Vec<Url>>,
-        _excluded: Arc<Vec<Url>>,
+        excluded: Arc<Vec<Url>>,
     ) -> Option<Url> {
         if invokers.is_empty() {
             return None;
         }
         let instance_count = invokers.len();
-        return if instance_count == 1 {
-            Some(invokers.as_ref().first()?.clone())
-        } else {
-            let loadbalance = Some(DEFAULT_LOADBALANCE);
-            self.do_select(loadbalance, invocation, invokers)
-        };
+        if instance_count == 1 {
+            return Some(invokers.as_ref().first()?.clone());
+        }
+        // method level loadbalance overrides the reference level one
+        let loadbalance = self
+            .url
+            .get_method_param(&invocation.get_method_name(), LOADBALANCE_KEY);
+        let loadbalance = loadbalance.as_deref();
+        let selected = self.do_select(loadbalance, invocation.clone(), invokers.clone())?;
+        if !excluded.contains(&selected) {
+            return Some(selected);
+        }
+        // reselect among the invokers not tried yet, fallback to the first pick if all failed
+        let reselect: Vec<Url> = invokers
+            .iter()
+            .filter(|url| !excluded.contains(url))
+            .cloned()
+            .collect();
+        if reselect.is_empty() {
+            return Some(selected);
+        }
+        self.do_select(loadbalance, invocation, Arc::new(reselect))
+            .or(Some(selected))
     }
 
     /// picking instance invoker url from registry directory
@@ -122,26 +302,28 @@ impl ClusterInvokerSelector for ClusterInvoker {
         invocation: Arc<RpcInvocation>,
         invokers: Arc<Vec<Url>>,
     ) -> Option<Url> {
-        let loadbalance = self.init_loadbalance(loadbalance_key.unwrap_or(DEFAULT_LOADBALANCE));
-        loadbalance.select(invokers, None, invocation)
+        let loadbalance = self.init_loadbalance(loadbalance_key.unwrap_or(DEFAULT_LOADBALANCE))?;
+        loadbalance.select(invokers, Some(self.url.clone()), invocation)
     }
 }
 
-impl ClusterRequestBuilder for ClusterInvoker {
-    fn build_req(
-        &self,
-        triple_client: &mut TripleClient,
-        path: PathAndQuery,
-        invocation: Arc<RpcInvocation>,
-        body: SdkBody,
-    ) -> Request<SdkBody> {
-        let invokers = self.directory.list(invocation.clone());
-        let invoker_url = self
-            .select(invocation, Arc::new(invokers), Arc::new(Vec::new()))
-            .expect("no valid provider");
-        let http_uri =
-            http::Uri::from_str(&format!("http://{}:{}/", invoker_url.ip, invoker_url.port))
-                .unwrap();
-        triple_client.map_request(http_uri, path, body)
+fn route_request(
+    req: http::Request<SdkBody>,
+    uri: &http::Uri,
+) -> Result<http::Request<SdkBody>, crate::Error> {
+    let (mut parts, body) = req.into_parts();
+    let mut uri_parts = uri.clone().into_parts();
+    uri_parts.path_and_query = parts.uri.path_and_query().cloned();
+    parts.uri = http::Uri::from_parts(uri_parts)?;
+    if let Some(authority) = uri.authority() {
+        parts
+            .headers
+            .insert("authority", HeaderValue::from_str(authority.as_str())?);
+    }
+    if let Some(scheme) = uri.scheme_str() {
+        parts
+            .headers
+            .insert("scheme", HeaderValue::from_str(scheme)?);
     }
+    Ok(http::Request::from_parts(parts, body))
 }
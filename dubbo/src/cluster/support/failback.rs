@@ -0,0 +1,183 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    task::Poll,
+    time::Duration,
+};
+
+use aws_smithy_http::body::SdkBody;
+use dubbo_base::constants::{FAILBACK_TASKS_KEY, RETRIES_KEY};
+use dubbo_logger::tracing;
+use tower_service::Service;
+
+use crate::{
+    cluster::support::{
+        clone_request, cluster_invoker::ClusterInvoker, empty_response, get_invocation,
+        invoke_once, Failures,
+    },
+    invocation::{Invocation, RpcInvocation},
+};
+
+pub const DEFAULT_FAILBACK_TIMES: usize = 3;
+pub const DEFAULT_FAILBACK_TASKS: usize = 100;
+pub const RETRY_FAILED_PERIOD: Duration = Duration::from_secs(5);
+
+/// When fails, record failure requests and schedule for retry on a regular interval.
+/// Especially useful for services of notification.
+///
+/// Any status but OK of the provider is a failure, as well as a transport error. At most
+/// `failbacktasks` requests are pending for retry, the others are dropped.
+#[derive(Debug, Clone)]
+pub struct FailbackCluster {
+    invoker: ClusterInvoker,
+    pending: Arc<AtomicUsize>,
+}
+
+impl FailbackCluster {
+    pub fn new(invoker: ClusterInvoker) -> FailbackCluster {
+        Self {
+            invoker,
+            pending: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    fn add_failed(&self, invocation: Arc<RpcInvocation>, req: http::Request<SdkBody>) {
+        let method = invocation.get_method_name();
+        let capacity = self
+            .invoker
+            .url()
+            .get_method_param(&method, FAILBACK_TASKS_KEY)
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(DEFAULT_FAILBACK_TASKS);
+        if self.pending.fetch_add(1, Ordering::SeqCst) >= capacity {
+            self.pending.fetch_sub(1, Ordering::SeqCst);
+            tracing::error!(
+                "failback tasks of {} exceed {}, drop the failed request",
+                invocation.unique_fingerprint(),
+                capacity
+            );
+            return;
+        }
+        let times = self
+            .invoker
+            .url()
+            .get_method_param(&method, RETRIES_KEY)
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(DEFAULT_FAILBACK_TIMES);
+
+        let invoker = self.invoker.clone();
+        let pending = self.pending.clone();
+        tokio::spawn(async move {
+            for i in 1..=times {
+                tokio::time::sleep(RETRY_FAILED_PERIOD).await;
+                let attempt = match clone_request(&req) {
+                    Some(v) => v,
+                    None => break,
+                };
+                match invoke_once(&invoker, invocation.clone(), attempt, Failures::NotOk).await {
+                    Ok(_) => break,
+                    Err(status) => tracing::error!(
+                        "failback retry {} of {} failed: {}",
+                        i,
+                        invocation.unique_fingerprint(),
+                        status
+                    ),
+                }
+            }
+            pending.fetch_sub(1, Ordering::SeqCst);
+        });
+    }
+}
+
+impl Service<http::Request<SdkBody>> for FailbackCluster {
+    type Response = http::Response<crate::BoxBody>;
+
+    type Error = crate::Error;
+
+    type Future = crate::BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(
+        &mut self,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<SdkBody>) -> Self::Future {
+        let cluster = self.clone();
+        Box::pin(async move {
+            let invocation = get_invocation(&req)?;
+            let retry_req = clone_request(&req);
+            match invoke_once(&cluster.invoker, invocation.clone(), req, Failures::NotOk).await {
+                Ok(resp) => Ok(resp),
+                Err(status) => {
+                    tracing::error!(
+                        "failback to retry {} later: {}",
+                        invocation.unique_fingerprint(),
+                        status
+                    );
+                    match retry_req {
+                        Some(req) => cluster.add_failed(invocation, req),
+                        None => tracing::warn!(
+                            "streaming request of {} can not be retried",
+                            invocation.unique_fingerprint()
+                        ),
+                    }
+                    Ok(empty_response())
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        cluster::support::test_util::{invoker, request, status_code, TestProvider},
+        status::Code,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn schedule_retry() {
+        let failed = TestProvider::start(Code::Unavailable).await;
+        let invoker = invoker(vec![failed.url.clone()], &[(FAILBACK_TASKS_KEY, "1")]);
+        let mut cluster = FailbackCluster::new(invoker);
+        let resp = cluster.call(request()).await.unwrap();
+        assert_eq!(status_code(&resp), Code::Ok);
+        assert_eq!(cluster.pending.load(Ordering::SeqCst), 1);
+        // over `failbacktasks`, the failed request is dropped
+        cluster.call(request()).await.unwrap();
+        assert_eq!(cluster.pending.load(Ordering::SeqCst), 1);
+        assert_eq!(failed.calls(), 2);
+    }
+
+    #[tokio::test]
+    async fn retry_error_status() {
+        let failed = TestProvider::start(Code::Internal).await;
+        let mut cluster = FailbackCluster::new(invoker(vec![failed.url.clone()], &[]));
+        let resp = cluster.call(request()).await.unwrap();
+        assert_eq!(status_code(&resp), Code::Ok);
+        assert_eq!(cluster.pending.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,88 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::task::Poll;
+
+use aws_smithy_http::body::SdkBody;
+use tower_service::Service;
+
+use crate::cluster::support::{
+    cluster_invoker::ClusterInvoker, get_invocation, invoke_once, Failures,
+};
+
+/// Execute exactly once, which means this policy will throw an exception immediately in case of
+/// an invocation error. Usually used for non-idempotent write operations.
+#[derive(Debug, Clone)]
+pub struct FailfastCluster {
+    invoker: ClusterInvoker,
+}
+
+impl FailfastCluster {
+    pub fn new(invoker: ClusterInvoker) -> FailfastCluster {
+        Self { invoker }
+    }
+}
+
+impl Service<http::Request<SdkBody>> for FailfastCluster {
+    type Response = http::Response<crate::BoxBody>;
+
+    type Error = crate::Error;
+
+    type Future = crate::BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(
+        &mut self,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<SdkBody>) -> Self::Future {
+        let invoker = self.invoker.clone();
+        Box::pin(async move {
+            let invocation = get_invocation(&req)?;
+            invoke_once(&invoker, invocation, req, Failures::Unavailable)
+                .await
+                .map_err(|status| status.into())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dubbo_base::constants::RETRIES_KEY;
+
+    use crate::{
+        cluster::support::test_util::{invoker, request, TestProvider},
+        status::{Code, Status},
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn fail_without_retry() {
+        let failed = [
+            TestProvider::start(Code::Unavailable).await,
+            TestProvider::start(Code::Unavailable).await,
+        ];
+        let providers = failed.iter().map(|p| p.url.clone()).collect();
+        let mut cluster = FailfastCluster::new(invoker(providers, &[(RETRIES_KEY, "2")]));
+        let err = cluster.call(request()).await.err().unwrap();
+        assert_eq!(Status::from_error(err).code(), Code::Unavailable);
+        assert_eq!(failed.iter().map(|p| p.calls()).sum::<usize>(), 1);
+    }
+}
@@ -0,0 +1,161 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::{sync::Arc, task::Poll};
+
+use aws_smithy_http::body::SdkBody;
+use dubbo_base::{constants::RETRIES_KEY, Url};
+use dubbo_logger::tracing;
+use tower_service::Service;
+
+use crate::{
+    cluster::support::{
+        clone_request,
+        cluster_invoker::{ClusterInvoker, ClusterInvokerSelector},
+        get_invocation, invoke_provider, no_provider_status, Failures, DEFAULT_RETRIES,
+    },
+    invocation::Invocation,
+};
+
+/// When invoke fails, log the initial error and retry other invokers
+/// (retry `retries` times, which means at most `retries + 1` invokers will be invoked).
+/// Note that retry causes latency.
+///
+/// The invocation is carried by the request extensions, see [`TripleClient`](crate::codegen::TripleClient).
+#[derive(Debug, Clone)]
+pub struct FailoverCluster {
+    invoker: ClusterInvoker,
+}
+
+impl FailoverCluster {
+    pub fn new(invoker: ClusterInvoker) -> FailoverCluster {
+        Self { invoker }
+    }
+}
+
+impl Service<http::Request<SdkBody>> for FailoverCluster {
+    type Response = http::Response<crate::BoxBody>;
+
+    type Error = crate::Error;
+
+    type Future = crate::BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(
+        &mut self,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<SdkBody>) -> Self::Future {
+        let invoker = self.invoker.clone();
+        Box::pin(async move {
+            let invocation = get_invocation(&req)?;
+            let retries = invoker
+                .url()
+                .get_method_param(&invocation.get_method_name(), RETRIES_KEY)
+                .and_then(|v| v.parse::<usize>().ok())
+                .unwrap_or(DEFAULT_RETRIES);
+
+            let mut origin = Some(req);
+            let mut invoked: Vec<Url> = Vec::new();
+            let mut last_status = no_provider_status(&invocation);
+            for _ in 0..=retries {
+                let invokers = invoker.list(invocation.clone());
+                let provider = match invoker.select(
+                    invocation.clone(),
+                    Arc::new(invokers),
+                    Arc::new(invoked.clone()),
+                ) {
+                    Some(v) => v,
+                    None => break,
+                };
+                // a streaming body can not be replayed, it is sent once without retry
+                let attempt = match origin.as_ref().and_then(clone_request) {
+                    Some(v) => v,
+                    None => match origin.take() {
+                        Some(v) => v,
+                        None => break,
+                    },
+                };
+
+                match invoke_provider(&invoker, &provider, attempt, Failures::Unavailable).await {
+                    Ok(resp) => return Ok(resp),
+                    Err(status) => last_status = status,
+                }
+                tracing::warn!(
+                    "failover invoke {} on {} failed: {}",
+                    invocation.unique_fingerprint(),
+                    provider,
+                    last_status
+                );
+                invoked.push(provider);
+            }
+            Err(last_status.into())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        cluster::support::test_util::{closed_provider, invoker, request, TestProvider},
+        status::{Code, Status},
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn retry_other_providers() {
+        let failed = [
+            TestProvider::start(Code::Unavailable).await,
+            TestProvider::start(Code::Unavailable).await,
+        ];
+        let ok = TestProvider::start(Code::Ok).await;
+        let providers = vec![failed[0].url.clone(), failed[1].url.clone(), ok.url.clone()];
+        let mut cluster = FailoverCluster::new(invoker(providers, &[(RETRIES_KEY, "2")]));
+        assert!(cluster.call(request()).await.is_ok());
+        // each provider is tried once at most
+        assert!(failed.iter().all(|provider| provider.calls() <= 1));
+        assert_eq!(ok.calls(), 1);
+    }
+
+    #[tokio::test]
+    async fn retries_limit_attempts() {
+        let failed = [
+            TestProvider::start(Code::Unavailable).await,
+            TestProvider::start(Code::Unavailable).await,
+            TestProvider::start(Code::Unavailable).await,
+        ];
+        let providers = failed.iter().map(|p| p.url.clone()).collect();
+        let mut cluster = FailoverCluster::new(invoker(providers, &[(RETRIES_KEY, "1")]));
+        let err = cluster.call(request()).await.err().unwrap();
+        assert_eq!(Status::from_error(err).code(), Code::Unavailable);
+        assert_eq!(failed.iter().map(|p| p.calls()).sum::<usize>(), 2);
+    }
+
+    #[tokio::test]
+    async fn unreachable_provider_is_excluded() {
+        let ok = TestProvider::start(Code::Ok).await;
+        let providers = vec![closed_provider(), ok.url.clone()];
+        let mut cluster = FailoverCluster::new(invoker(providers, &[(RETRIES_KEY, "1")]));
+        for _ in 0..5 {
+            assert!(cluster.call(request()).await.is_ok());
+        }
+        assert_eq!(ok.calls(), 5);
+    }
+}
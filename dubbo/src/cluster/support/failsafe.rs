@@ -0,0 +1,107 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::task::Poll;
+
+use aws_smithy_http::body::SdkBody;
+use dubbo_logger::tracing;
+use tower_service::Service;
+
+use crate::cluster::support::{
+    cluster_invoker::ClusterInvoker, empty_response, get_invocation, invoke_once, Failures,
+};
+
+/// When invoke fails, log the error message and return an empty response.
+/// Usually used to write audit logs and other operations.
+///
+/// Any status but OK of the provider is a failure, as well as a transport error.
+#[derive(Debug, Clone)]
+pub struct FailsafeCluster {
+    invoker: ClusterInvoker,
+}
+
+impl FailsafeCluster {
+    pub fn new(invoker: ClusterInvoker) -> FailsafeCluster {
+        Self { invoker }
+    }
+}
+
+impl Service<http::Request<SdkBody>> for FailsafeCluster {
+    type Response = http::Response<crate::BoxBody>;
+
+    type Error = crate::Error;
+
+    type Future = crate::BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(
+        &mut self,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<SdkBody>) -> Self::Future {
+        let invoker = self.invoker.clone();
+        Box::pin(async move {
+            let invocation = get_invocation(&req)?;
+            match invoke_once(&invoker, invocation.clone(), req, Failures::NotOk).await {
+                Ok(resp) => Ok(resp),
+                Err(status) => {
+                    tracing::error!(
+                        "failsafe ignore error of {}: {}",
+                        invocation.unique_fingerprint(),
+                        status
+                    );
+                    Ok(empty_response())
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http_body::Body;
+
+    use crate::{
+        cluster::support::test_util::{
+            closed_provider, invoker, request, status_code, TestProvider,
+        },
+        status::Code,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn ignore_error() {
+        let mut cluster = FailsafeCluster::new(invoker(vec![closed_provider()], &[]));
+        let mut resp = cluster.call(request()).await.unwrap();
+        assert_eq!(status_code(&resp), Code::Ok);
+        // a single empty message
+        let data = resp.data().await.unwrap().unwrap();
+        assert_eq!(&data[..], &[0u8; 5]);
+    }
+
+    #[tokio::test]
+    async fn ignore_error_status() {
+        let failed = TestProvider::start(Code::Internal).await;
+        let mut cluster = FailsafeCluster::new(invoker(vec![failed.url.clone()], &[]));
+        let resp = cluster.call(request()).await.unwrap();
+        assert_eq!(status_code(&resp), Code::Ok);
+        assert_eq!(failed.calls(), 1);
+    }
+}
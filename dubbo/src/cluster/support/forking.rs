@@ -0,0 +1,176 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::{sync::Arc, task::Poll, time::Duration};
+
+use aws_smithy_http::body::SdkBody;
+use dubbo_base::{
+    constants::{FORKS_KEY, TIMEOUT_KEY},
+    Url,
+};
+use dubbo_logger::tracing;
+use futures_util::future::select_ok;
+use tower_service::Service;
+
+use crate::{
+    cluster::support::{
+        clone_request,
+        cluster_invoker::{ClusterInvoker, ClusterInvokerSelector},
+        get_invocation, invoke_provider, no_provider_status, Failures,
+    },
+    invocation::Invocation,
+    status::{Code, Status},
+};
+
+pub const DEFAULT_FORKS: usize = 2;
+pub const DEFAULT_FORKING_TIMEOUT: u64 = 1000;
+
+/// Invoke a specific number of invokers concurrently, usually used for demanding real-time
+/// operations, but need to waste more service resources.
+///
+/// The first successful response wins and the other calls are cancelled, the error of the last
+/// failed call is returned when all fail. The whole calls are bounded by `timeout` milliseconds.
+#[derive(Debug, Clone)]
+pub struct ForkingCluster {
+    invoker: ClusterInvoker,
+}
+
+impl ForkingCluster {
+    pub fn new(invoker: ClusterInvoker) -> ForkingCluster {
+        Self { invoker }
+    }
+}
+
+impl Service<http::Request<SdkBody>> for ForkingCluster {
+    type Response = http::Response<crate::BoxBody>;
+
+    type Error = crate::Error;
+
+    type Future = crate::BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(
+        &mut self,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<SdkBody>) -> Self::Future {
+        let invoker = self.invoker.clone();
+        Box::pin(async move {
+            let invocation = get_invocation(&req)?;
+            let method = invocation.get_method_name();
+            let forks = invoker
+                .url()
+                .get_method_param(&method, FORKS_KEY)
+                .and_then(|v| v.parse::<usize>().ok())
+                .unwrap_or(DEFAULT_FORKS);
+            let timeout = invoker
+                .url()
+                .get_method_param(&method, TIMEOUT_KEY)
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or(DEFAULT_FORKING_TIMEOUT);
+
+            let invokers = Arc::new(invoker.list(invocation.clone()));
+            let mut selected: Vec<Url> = Vec::new();
+            while selected.len() < forks.min(invokers.len()) {
+                let provider = match invoker.select(
+                    invocation.clone(),
+                    invokers.clone(),
+                    Arc::new(selected.clone()),
+                ) {
+                    Some(v) if !selected.contains(&v) => v,
+                    _ => break,
+                };
+                selected.push(provider);
+            }
+            if selected.is_empty() {
+                return Err(no_provider_status(&invocation).into());
+            }
+
+            // a streaming body can not be replayed, it is sent to one provider only
+            let mut origin = Some(req);
+            let mut calls = Vec::with_capacity(selected.len());
+            for provider in selected {
+                let attempt = match origin.as_ref().and_then(clone_request) {
+                    Some(v) => v,
+                    None => match origin.take() {
+                        Some(v) => v,
+                        None => break,
+                    },
+                };
+                let invoker = invoker.clone();
+                // any error status of a trailers-only response fails the fork
+                calls.push(Box::pin(async move {
+                    invoke_provider(&invoker, &provider, attempt, Failures::NotOk).await
+                }));
+            }
+
+            match tokio::time::timeout(Duration::from_millis(timeout), select_ok(calls)).await {
+                Ok(Ok((resp, _rest))) => Ok(resp),
+                Ok(Err(status)) => {
+                    tracing::warn!(
+                        "forking invoke {} failed: {}",
+                        invocation.unique_fingerprint(),
+                        status
+                    );
+                    Err(status.into())
+                }
+                Err(_) => Err(Status::new(
+                    Code::DeadlineExceeded,
+                    format!(
+                        "forking invoke {} timeout after {}ms",
+                        invocation.unique_fingerprint(),
+                        timeout
+                    ),
+                )
+                .into()),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cluster::support::test_util::{invoker, request, status_code, TestProvider};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn first_success_wins() {
+        let failed = TestProvider::start(Code::Internal).await;
+        let ok = TestProvider::start_with_delay(Code::Ok, Duration::from_millis(100)).await;
+        let providers = vec![failed.url.clone(), ok.url.clone()];
+        let mut cluster = ForkingCluster::new(invoker(providers, &[(FORKS_KEY, "2")]));
+        let resp = cluster.call(request()).await.unwrap();
+        assert_eq!(status_code(&resp), Code::Ok);
+        assert_eq!(failed.calls(), 1);
+        assert_eq!(ok.calls(), 1);
+    }
+
+    #[tokio::test]
+    async fn all_fail_returns_last_error() {
+        let first = TestProvider::start(Code::Internal).await;
+        let last =
+            TestProvider::start_with_delay(Code::PermissionDenied, Duration::from_millis(100))
+                .await;
+        let providers = vec![first.url.clone(), last.url.clone()];
+        let mut cluster = ForkingCluster::new(invoker(providers, &[(FORKS_KEY, "2")]));
+        let err = cluster.call(request()).await.err().unwrap();
+        assert_eq!(Status::from_error(err).code(), Code::PermissionDenied);
+    }
+}
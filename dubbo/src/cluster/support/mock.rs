@@ -0,0 +1,307 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::{
+    fmt::{Debug, Formatter},
+    sync::Arc,
+    task::Poll,
+};
+
+use aws_smithy_http::body::SdkBody;
+use bytes::Bytes;
+use dubbo_base::{constants::MOCK_KEY, Url};
+use dubbo_logger::tracing;
+use tower::ServiceExt;
+use tower_service::Service;
+
+use crate::{
+    cluster::{
+        support::{get_invocation, message_response},
+        ClusterBoxService,
+    },
+    invocation::{Invocation, RpcInvocation},
+    status::{Code, Status},
+};
+
+type FallbackFn = dyn Fn(Arc<RpcInvocation>, Status) -> Result<Bytes, Status> + Send + Sync;
+
+/// Builds the encoded response message of a failed call from the call and its status.
+#[derive(Clone)]
+pub struct Fallback(Arc<FallbackFn>);
+
+impl Fallback {
+    pub fn new<F>(f: F) -> Self
+    where
+        F: Fn(Arc<RpcInvocation>, Status) -> Result<Bytes, Status> + Send + Sync + 'static,
+    {
+        Fallback(Arc::new(f))
+    }
+}
+
+impl Debug for Fallback {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Fallback")
+    }
+}
+
+/// The `mock` param of a reference, or `{method}.mock` of a method:
+///
+/// - `force:return <value>` returns the value without calling the providers.
+/// - `fail:return <value>` returns the value when the call fails.
+/// - `fail` or `true` calls the fallback of the client when the call fails, which is the
+///   default once a fallback is given.
+/// - `false` disables the mock.
+///
+/// Only the calls failing with `Unavailable` or `DeadlineExceeded` are degraded. The value is
+/// sent as is as the encoded response message, eg. a json text for the serde codec, `empty`
+/// and `null` are the default message. A text is not valid protobuf, so with the prost codec
+/// only `empty` and `null` are usable, a fallback builds any other message.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Mock {
+    ForceReturn(Bytes),
+    FailReturn(Bytes),
+    Fallback,
+}
+
+impl Mock {
+    /// Parses the value of a `mock` param, `None` when the mock is disabled.
+    pub fn parse(value: &str) -> Result<Option<Mock>, crate::StdError> {
+        let value = value.trim();
+        let returned = |value: &str| match value.trim() {
+            "" | "empty" | "null" => Bytes::new(),
+            value => Bytes::copy_from_slice(value.as_bytes()),
+        };
+        if value.is_empty() || value == "false" {
+            return Ok(None);
+        }
+        if value == "true" || value == "fail" {
+            return Ok(Some(Mock::Fallback));
+        }
+        if let Some(v) = value.strip_prefix("force:return") {
+            return Ok(Some(Mock::ForceReturn(returned(v))));
+        }
+        if let Some(v) = value.strip_prefix("fail:return") {
+            return Ok(Some(Mock::FailReturn(returned(v))));
+        }
+        Err(format!("invalid mock {}", value).into())
+    }
+}
+
+/// Degrades the calls of a cluster by the mock of their method, see [`Mock`].
+#[derive(Clone)]
+pub struct MockCluster {
+    inner: ClusterBoxService,
+    url: Url,
+    fallback: Option<Fallback>,
+}
+
+impl Debug for MockCluster {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MockCluster")
+            .field("url", &self.url)
+            .field("fallback", &self.fallback)
+            .finish()
+    }
+}
+
+impl MockCluster {
+    pub fn new(inner: ClusterBoxService, url: Url, fallback: Option<Fallback>) -> Self {
+        MockCluster {
+            inner,
+            url,
+            fallback,
+        }
+    }
+
+    fn mock(&self, method: &str) -> Option<Mock> {
+        match self.url.get_method_param(method, MOCK_KEY) {
+            // checked when the client is built
+            Some(value) => Mock::parse(&value).ok().flatten(),
+            None if self.fallback.is_some() => Some(Mock::Fallback),
+            None => None,
+        }
+    }
+}
+
+fn degraded(code: Code) -> bool {
+    matches!(code, Code::Unavailable | Code::DeadlineExceeded)
+}
+
+impl Service<http::Request<SdkBody>> for MockCluster {
+    type Response = http::Response<crate::BoxBody>;
+
+    type Error = crate::Error;
+
+    type Future = crate::BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(
+        &mut self,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<SdkBody>) -> Self::Future {
+        let inner = self.inner.clone();
+        let fallback = self.fallback.clone();
+        let invocation = match get_invocation(&req) {
+            Ok(v) => v,
+            Err(err) => return Box::pin(async move { Err(err) }),
+        };
+        let mock = self.mock(&invocation.get_method_name());
+        Box::pin(async move {
+            let mock = match mock {
+                Some(Mock::ForceReturn(message)) => return Ok(message_response(message)),
+                Some(v) => v,
+                None => return inner.oneshot(req).await,
+            };
+            let status = match inner.oneshot(req).await {
+                Ok(resp) => match Status::from_header_map(resp.headers()) {
+                    Some(status) if degraded(status.code()) => status,
+                    _ => return Ok(resp),
+                },
+                Err(err) => {
+                    let status = Status::from_error(err);
+                    if !degraded(status.code()) {
+                        return Err(status.into());
+                    }
+                    status
+                }
+            };
+            tracing::warn!(
+                "mock the failed call of {}: {}",
+                invocation.unique_fingerprint(),
+                status
+            );
+            let message = match (mock, fallback) {
+                (Mock::FailReturn(message), _) => message,
+                (_, Some(fallback)) => (fallback.0)(invocation, status)?,
+                (_, None) => return Err(status.into()),
+            };
+            Ok(message_response(message))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use dubbo_base::constants::CLUSTER_KEY;
+    use http_body::Body;
+
+    use super::*;
+    use crate::cluster::support::test_util::{invoker, request, status_code, TestProvider, METHOD};
+
+    #[test]
+    fn parse_mock() {
+        assert_eq!(Mock::parse("").unwrap(), None);
+        assert_eq!(Mock::parse("false").unwrap(), None);
+        assert_eq!(Mock::parse("fail").unwrap(), Some(Mock::Fallback));
+        assert_eq!(
+            Mock::parse("force:return null").unwrap(),
+            Some(Mock::ForceReturn(Bytes::new()))
+        );
+        assert_eq!(
+            Mock::parse("fail:return {\"message\": \"degraded\"}").unwrap(),
+            Some(Mock::FailReturn(Bytes::from_static(
+                b"{\"message\": \"degraded\"}"
+            )))
+        );
+        assert!(Mock::parse("force:throw").is_err());
+    }
+
+    /// The message of a response carrying a single one.
+    async fn message(resp: http::Response<crate::BoxBody>) -> Bytes {
+        let mut body = resp.into_body();
+        let data = body.data().await.unwrap().unwrap();
+        data.slice(5..)
+    }
+
+    async fn call(cluster: ClusterBoxService) -> Result<http::Response<crate::BoxBody>, Code> {
+        cluster
+            .oneshot(request())
+            .await
+            .map_err(|err| Status::from_error(err).code())
+    }
+
+    #[tokio::test]
+    async fn force_return_skips_providers() {
+        let provider = TestProvider::start(Code::Ok).await;
+        let params = [(MOCK_KEY, "force:return forced")];
+        let resp = call(invoker(vec![provider.url.clone()], &params).join())
+            .await
+            .unwrap();
+        assert_eq!(status_code(&resp), Code::Ok);
+        assert_eq!(message(resp).await, Bytes::from_static(b"forced"));
+        assert_eq!(provider.calls(), 0);
+    }
+
+    #[tokio::test]
+    async fn fail_return_degrades_unavailable() {
+        let params = [
+            (CLUSTER_KEY, "failfast"),
+            (MOCK_KEY, "fail:return degraded"),
+        ];
+        for code in [Code::Unavailable, Code::DeadlineExceeded] {
+            let provider = TestProvider::start(code).await;
+            let resp = call(invoker(vec![provider.url.clone()], &params).join())
+                .await
+                .unwrap();
+            assert_eq!(status_code(&resp), Code::Ok);
+            assert_eq!(message(resp).await, Bytes::from_static(b"degraded"));
+            assert_eq!(provider.calls(), 1);
+        }
+        // the other errors are returned as they are
+        for code in [Code::Internal, Code::NotFound] {
+            let provider = TestProvider::start(code).await;
+            let resp = call(invoker(vec![provider.url.clone()], &params).join()).await;
+            assert_eq!(resp.map(|resp| status_code(&resp)), Ok(code));
+        }
+    }
+
+    #[tokio::test]
+    async fn fallback_builds_response() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let recorded = calls.clone();
+        let fallback = Fallback::new(move |invocation, status| {
+            recorded
+                .lock()
+                .unwrap()
+                .push((invocation.get_method_name(), status.code()));
+            Ok(Bytes::from_static(b"fallback"))
+        });
+        let cluster = |provider: &TestProvider| {
+            invoker(vec![provider.url.clone()], &[(CLUSTER_KEY, "failfast")])
+                .with_fallback(fallback.clone())
+                .join()
+        };
+
+        let failed = TestProvider::start(Code::Unavailable).await;
+        let resp = call(cluster(&failed)).await.unwrap();
+        assert_eq!(message(resp).await, Bytes::from_static(b"fallback"));
+        assert_eq!(
+            *calls.lock().unwrap(),
+            vec![(METHOD.to_string(), Code::Unavailable)]
+        );
+
+        let not_found = TestProvider::start(Code::NotFound).await;
+        let resp = call(cluster(&not_found)).await.unwrap();
+        assert_eq!(status_code(&resp), Code::NotFound);
+        assert_eq!(calls.lock().unwrap().len(), 1);
+    }
+}
@@ -15,6 +15,251 @@
  * limitations under the License.
  */
 
+use std::{collections::HashMap, sync::Arc};
+
+use aws_smithy_http::body::SdkBody;
+use bytes::{BufMut, Bytes, BytesMut};
+use dubbo_base::{
+    constants::{
+        CLUSTER_KEY, CONNECTIONS_KEY, HASH_FIELD_KEY, LOADBALANCE_KEY, MAX_CONCURRENT_STREAMS_KEY,
+        MOCK_KEY,
+    },
+    Url,
+};
+use http_body::Body;
+use lazy_static::lazy_static;
+
+use crate::{
+    cluster::{loadbalance::LOAD_BALANCE_EXTENSIONS, ClusterBoxService},
+    invocation::{Invocation, RpcInvocation},
+    status::{Code, Status, GRPC_STATUS},
+};
+
+use self::{
+    broadcast::BroadcastCluster,
+    cluster_invoker::{ClusterInvoker, ClusterInvokerSelector},
+    failback::FailbackCluster,
+    failfast::FailfastCluster,
+    failover::FailoverCluster,
+    failsafe::FailsafeCluster,
+    forking::ForkingCluster,
+};
+
+pub mod broadcast;
 pub mod cluster_invoker;
+pub mod failback;
+pub mod failfast;
+pub mod failover;
+pub mod failsafe;
+pub mod forking;
+pub mod mock;
+#[cfg(test)]
+pub(crate) mod test_util;
 
 pub const DEFAULT_LOADBALANCE: &str = "random";
+pub const DEFAULT_CLUSTER: &str = "failover";
+pub const DEFAULT_RETRIES: usize = 2;
+
+pub type ClusterJoin = fn(ClusterInvoker) -> ClusterBoxService;
+
+lazy_static! {
+    pub static ref CLUSTER_EXTENSIONS: HashMap<String, ClusterJoin> = init_cluster_extensions();
+}
+
+fn init_cluster_extensions() -> HashMap<String, ClusterJoin> {
+    let mut cluster_map: HashMap<String, ClusterJoin> = HashMap::new();
+    cluster_map.insert("failover".to_string(), |invoker| {
+        ClusterBoxService::new(FailoverCluster::new(invoker))
+    });
+    cluster_map.insert("failfast".to_string(), |invoker| {
+        ClusterBoxService::new(FailfastCluster::new(invoker))
+    });
+    cluster_map.insert("failsafe".to_string(), |invoker| {
+        ClusterBoxService::new(FailsafeCluster::new(invoker))
+    });
+    cluster_map.insert("failback".to_string(), |invoker| {
+        ClusterBoxService::new(FailbackCluster::new(invoker))
+    });
+    cluster_map.insert("forking".to_string(), |invoker| {
+        ClusterBoxService::new(ForkingCluster::new(invoker))
+    });
+    cluster_map.insert("broadcast".to_string(), |invoker| {
+        ClusterBoxService::new(BroadcastCluster::new(invoker))
+    });
+    cluster_map
+}
+
+/// Checks the params of the reference url: the cluster and loadbalance names, including the
+/// method level ones, must be registered extensions, the mocks must parse, and the connection
+/// counts and hash fields must be positive.
+pub(crate) fn check_url(url: &Url) -> Result<(), crate::StdError> {
+    for (key, value) in url.params.iter() {
+        if key == CLUSTER_KEY && !CLUSTER_EXTENSIONS.contains_key(value) {
+            return Err(format!("unknown cluster {}", value).into());
+        }
+        let is_loadbalance =
+            key == LOADBALANCE_KEY || key.ends_with(&format!(".{}", LOADBALANCE_KEY));
+        if is_loadbalance && !LOAD_BALANCE_EXTENSIONS.contains_key(value) {
+            return Err(format!("unknown loadbalance {} of {}", value, key).into());
+        }
+        if key == MOCK_KEY || key.ends_with(&format!(".{}", MOCK_KEY)) {
+            mock::Mock::parse(value).map_err(|err| format!("{} of {}", err, key))?;
+        }
+        let is_count = key == CONNECTIONS_KEY
+            || key == MAX_CONCURRENT_STREAMS_KEY
+            || key == HASH_FIELD_KEY
+            || key.ends_with(&format!(".{}", HASH_FIELD_KEY));
+        if is_count && value.parse::<u32>().map_or(true, |v| v == 0) {
+            return Err(format!("{} must be a positive integer, got {}", key, value).into());
+        }
+    }
+    Ok(())
+}
+
+/// Returns the invocation inserted into the request extensions by the client.
+pub(crate) fn get_invocation(
+    req: &http::Request<SdkBody>,
+) -> Result<Arc<RpcInvocation>, crate::Error> {
+    req.extensions()
+        .get::<Arc<RpcInvocation>>()
+        .cloned()
+        .ok_or_else(|| {
+            Status::new(
+                Code::Internal,
+                "missing invocation in request extensions".to_string(),
+            )
+            .into()
+        })
+}
+
+/// Clones `req` for another attempt, returns `None` when the body can not be replayed.
+pub(crate) fn clone_request(req: &http::Request<SdkBody>) -> Option<http::Request<SdkBody>> {
+    let body = req.body().try_clone()?;
+    let mut clone_req = http::Request::new(body);
+    *clone_req.method_mut() = req.method().clone();
+    *clone_req.uri_mut() = req.uri().clone();
+    *clone_req.version_mut() = req.version();
+    *clone_req.headers_mut() = req.headers().clone();
+    Some(clone_req)
+}
+
+pub(crate) fn no_provider_status(invocation: &RpcInvocation) -> Status {
+    Status::new(
+        Code::Unavailable,
+        format!(
+            "no provider available for {}",
+            invocation.get_target_service_unique_name()
+        ),
+    )
+}
+
+/// The statuses of a provider which a cluster handles as a failed call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Failures {
+    /// `Unavailable` only, the call may succeed on another provider while the other errors
+    /// are returned to the caller as they are.
+    Unavailable,
+    /// Any status but `OK`, for the clusters which replace or combine the failed responses.
+    NotOk,
+}
+
+impl Failures {
+    pub(crate) fn contains(self, code: Code) -> bool {
+        match self {
+            Failures::Unavailable => code == Code::Unavailable,
+            Failures::NotOk => code != Code::Ok,
+        }
+    }
+}
+
+/// The status of a trailers-only response, which carries the status in its headers.
+pub(crate) fn header_status(resp: &http::Response<crate::BoxBody>) -> Option<Status> {
+    Status::from_header_map(resp.headers())
+}
+
+/// Invokes `provider`, transport errors and the statuses of `failures` are reported as `Err`
+/// so that the cluster is able to try another provider or to handle the failure.
+pub(crate) async fn invoke_provider(
+    invoker: &ClusterInvoker,
+    provider: &Url,
+    req: http::Request<SdkBody>,
+    failures: Failures,
+) -> Result<http::Response<crate::BoxBody>, Status> {
+    match invoker.invoke(provider, req).await {
+        Ok(resp) => match header_status(&resp) {
+            Some(status) if failures.contains(status.code()) => Err(status),
+            _ => Ok(resp),
+        },
+        Err(err) => Err(Status::new(Code::Unavailable, err.to_string())),
+    }
+}
+
+/// Lists and selects a provider, then invokes it once.
+pub(crate) async fn invoke_once(
+    invoker: &ClusterInvoker,
+    invocation: Arc<RpcInvocation>,
+    req: http::Request<SdkBody>,
+    failures: Failures,
+) -> Result<http::Response<crate::BoxBody>, Status> {
+    let invokers = invoker.list(invocation.clone());
+    let provider = invoker
+        .select(invocation.clone(), Arc::new(invokers), Arc::new(Vec::new()))
+        .ok_or_else(|| no_provider_status(&invocation))?;
+    invoke_provider(invoker, &provider, req, failures).await
+}
+
+/// An OK response carrying a single empty message, which is decoded as the default message.
+pub(crate) fn empty_response() -> http::Response<crate::BoxBody> {
+    message_response(Bytes::new())
+}
+
+/// An OK response carrying the single encoded `message`.
+pub(crate) fn message_response(message: Bytes) -> http::Response<crate::BoxBody> {
+    // uncompressed flag and message length
+    let mut frame = BytesMut::with_capacity(5 + message.len());
+    frame.put_u8(0);
+    frame.put_u32(message.len() as u32);
+    frame.put(message);
+    let body = http_body::Full::new(frame.freeze())
+        .map_err(|err| match err {})
+        .boxed_unsync();
+    http::Response::builder()
+        .status(200)
+        .header(http::header::CONTENT_TYPE, "application/grpc")
+        .header(GRPC_STATUS, Code::Ok.to_http_header_value())
+        .body(body)
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{test_util::*, *};
+    use crate::triple::client::builder::ClientBuilder;
+
+    fn url(params: &[(&str, &str)]) -> Url {
+        let mut url = Url::new();
+        for (k, v) in params {
+            url.set_param(k, v);
+        }
+        url
+    }
+
+    #[test]
+    fn check_extension_names() {
+        assert!(check_url(&url(&[(CLUSTER_KEY, "forking"), (LOADBALANCE_KEY, "p2c")])).is_ok());
+        assert!(check_url(&url(&[(CLUSTER_KEY, "failfats")])).is_err());
+        assert!(check_url(&url(&[(LOADBALANCE_KEY, "randon")])).is_err());
+        assert!(check_url(&url(&[("Call.loadbalance", "roundrobin")])).is_ok());
+        assert!(check_url(&url(&[("Call.loadbalance", "leastactiv")])).is_err());
+        assert!(check_url(&url(&[("Call.hash.field", "2")])).is_ok());
+        assert!(check_url(&url(&[(HASH_FIELD_KEY, "name")])).is_err());
+    }
+
+    #[test]
+    fn reject_unknown_cluster() {
+        let url = url(&[(CLUSTER_KEY, "failfats")]);
+        let invoker = ClusterInvoker::with_directory(Box::new(TestDirectory(Vec::new())));
+        assert!(invoker.with_url(url.clone()).is_err());
+        assert!(ClientBuilder::new().with_url(url).is_err());
+    }
+}
@@ -0,0 +1,211 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Providers and requests shared by the tests of the clusters.
+
+use std::{
+    convert::Infallible,
+    net::TcpListener,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use aws_smithy_http::body::SdkBody;
+use dubbo_base::Url;
+use hyper::service::{make_service_fn, service_fn};
+use tokio_rustls::{rustls::ServerConfig, TlsAcceptor};
+
+use crate::{
+    cluster::{directory::Directory, support::cluster_invoker::ClusterInvoker},
+    invocation::RpcInvocation,
+    status::{Code, GRPC_STATUS},
+};
+
+pub(crate) const SERVICE: &str = "test.Service";
+pub(crate) const METHOD: &str = "Call";
+
+/// A provider answering every call with `code` after `delay`, the failures are trailers-only
+/// responses unless it is started by [`start_with_trailers`](Self::start_with_trailers).
+pub(crate) struct TestProvider {
+    pub(crate) url: Url,
+    calls: Arc<AtomicUsize>,
+}
+
+impl TestProvider {
+    pub(crate) async fn start(code: Code) -> Self {
+        Self::start_with_delay(code, Duration::ZERO).await
+    }
+
+    pub(crate) async fn start_with_delay(code: Code, delay: Duration) -> Self {
+        Self::serve(code, delay, false).await
+    }
+
+    /// A provider answering every call with a message followed by trailers carrying `code`.
+    pub(crate) async fn start_with_trailers(code: Code) -> Self {
+        Self::serve(code, Duration::ZERO, true).await
+    }
+
+    async fn serve(code: Code, delay: Duration, trailers: bool) -> Self {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let make = make_service_fn(move |_| {
+            let counter = counter.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |_: http::Request<hyper::Body>| {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    async move {
+                        tokio::time::sleep(delay).await;
+                        let resp = if trailers {
+                            response_with_trailers(code)
+                        } else {
+                            response(code)
+                        };
+                        Ok::<_, Infallible>(resp)
+                    }
+                }))
+            }
+        });
+        let server = hyper::Server::bind(&"127.0.0.1:0".parse().unwrap())
+            .http2_only(true)
+            .serve(make);
+        let url = provider_url(server.local_addr().port());
+        tokio::spawn(server);
+        TestProvider { url, calls }
+    }
+
+    /// A provider accepting only TLS connections by `config`.
+    pub(crate) async fn start_tls(code: Code, config: Arc<ServerConfig>) -> Self {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = provider_url(listener.local_addr().unwrap().port());
+        let acceptor = TlsAcceptor::from(config);
+        let counter = calls.clone();
+        tokio::spawn(async move {
+            while let Ok((io, _)) = listener.accept().await {
+                let acceptor = acceptor.clone();
+                let counter = counter.clone();
+                tokio::spawn(async move {
+                    let io = match acceptor.accept(io).await {
+                        Ok(v) => v,
+                        Err(_) => return,
+                    };
+                    let svc = service_fn(move |_: http::Request<hyper::Body>| {
+                        counter.fetch_add(1, Ordering::SeqCst);
+                        async move { Ok::<_, Infallible>(response(code)) }
+                    });
+                    let _ = hyper::server::conn::Http::new()
+                        .http2_only(true)
+                        .serve_connection(io, svc)
+                        .await;
+                });
+            }
+        });
+        TestProvider { url, calls }
+    }
+
+    pub(crate) fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+}
+
+/// A provider refusing the connections.
+pub(crate) fn closed_provider() -> Url {
+    let port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    provider_url(port)
+}
+
+fn provider_url(port: u16) -> Url {
+    Url::from_url(&format!("tri://127.0.0.1:{}/{}", port, SERVICE)).unwrap()
+}
+
+fn response(code: Code) -> http::Response<hyper::Body> {
+    let body = match code {
+        // a single empty message
+        Code::Ok => hyper::Body::from(vec![0u8; 5]),
+        _ => hyper::Body::empty(),
+    };
+    http::Response::builder()
+        .status(200)
+        .header(http::header::CONTENT_TYPE, "application/grpc")
+        .header(GRPC_STATUS, code.to_http_header_value())
+        .body(body)
+        .unwrap()
+}
+
+fn response_with_trailers(code: Code) -> http::Response<hyper::Body> {
+    let (mut sender, body) = hyper::Body::channel();
+    tokio::spawn(async move {
+        // a single empty message
+        sender.send_data(vec![0u8; 5].into()).await?;
+        let mut trailers = http::HeaderMap::new();
+        trailers.insert(GRPC_STATUS, code.to_http_header_value());
+        sender.send_trailers(trailers).await
+    });
+    http::Response::builder()
+        .status(200)
+        .header(http::header::CONTENT_TYPE, "application/grpc")
+        .body(body)
+        .unwrap()
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct TestDirectory(pub(crate) Vec<Url>);
+
+impl Directory for TestDirectory {
+    fn list(&self, _invocation: Arc<RpcInvocation>) -> Vec<Url> {
+        self.0.clone()
+    }
+}
+
+/// An invoker of `providers`, the reference url has `params`.
+pub(crate) fn invoker(providers: Vec<Url>, params: &[(&str, &str)]) -> ClusterInvoker {
+    let mut url = Url::new();
+    url.service_name = SERVICE.to_string();
+    for (key, value) in params {
+        url.set_param(key, value);
+    }
+    ClusterInvoker::with_directory(Box::new(TestDirectory(providers)))
+        .with_url(url)
+        .unwrap()
+}
+
+/// A unary request carrying its invocation.
+pub(crate) fn request() -> http::Request<SdkBody> {
+    let mut req = http::Request::builder()
+        .method("POST")
+        .uri(format!("http://127.0.0.1/{}/{}", SERVICE, METHOD))
+        .body(SdkBody::from(vec![0u8; 5]))
+        .unwrap();
+    let invocation = RpcInvocation::default()
+        .with_service_unique_name(SERVICE.to_string())
+        .with_method_name(METHOD.to_string())
+        .with_arguments(bytes::Bytes::new());
+    req.extensions_mut().insert(Arc::new(invocation));
+    req
+}
+
+/// The grpc status of a response, OK when it has none.
+pub(crate) fn status_code(resp: &http::Response<crate::BoxBody>) -> Code {
+    crate::status::Status::from_header_map(resp.headers()).map_or(Code::Ok, |status| status.code())
+}
@@ -28,7 +28,7 @@ pub use tower_service::Service;
 
 pub use super::{
     cluster::{
-        directory::{Directory, RegistryDirectory},
+        directory::{Directory, MultiRegistryDirectory, RegistryDirectory},
         support::cluster_invoker::ClusterInvoker,
     },
     empty_body,
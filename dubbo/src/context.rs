@@ -17,16 +17,50 @@
 
 use std::{
     collections::HashMap,
+    future::Future,
     sync::{Arc, Mutex},
     thread,
 };
 
+use dubbo_base::constants::{FORCE_TAG_KEY, TAG_KEY};
 use dubbo_logger::tracing;
 use serde_json::Value;
 use state::Container;
+use tokio_rustls::rustls::Certificate;
 
 pub static APPLICATION_CONTEXT: Container![Send + Sync] = <Container![Send + Sync]>::new();
 
+/// The request metadata passed on from an incoming request to the calls made while serving it.
+pub const PROPAGATED_KEYS: [&str; 2] = [TAG_KEY, FORCE_TAG_KEY];
+
+tokio::task_local! {
+    static PROPAGATED_ATTACHMENTS: HashMap<String, String>;
+    static PEER_IDENTITY: Option<PeerIdentity>;
+}
+
+/// The cert chain a mutual TLS client presented and the server verified, the end entity cert
+/// comes first. The certs are in DER format.
+#[derive(Debug, Clone)]
+pub struct PeerIdentity {
+    certs: Arc<Vec<Certificate>>,
+}
+
+impl PeerIdentity {
+    pub fn new(certs: Vec<Certificate>) -> Self {
+        PeerIdentity {
+            certs: Arc::new(certs),
+        }
+    }
+
+    pub fn certs(&self) -> &[Certificate] {
+        &self.certs
+    }
+
+    pub fn end_entity(&self) -> Option<&Certificate> {
+        self.certs.first()
+    }
+}
+
 ///
 /// All environment information of during the current call will put into the context
 /// on the filter composing process,and all configuration information will convert the parameters of URL instance.
@@ -55,15 +89,48 @@ impl Context for RpcContext {
                 let attachment = HashMap::<String, Value>::new();
                 let mutex = Arc::new(Mutex::new(attachment));
                 let mutex_clone = Arc::clone(&mutex);
-                APPLICATION_CONTEXT.set_local(move || {
-                    return Arc::clone(&mutex_clone);
-                });
+                APPLICATION_CONTEXT.set_local(move || Arc::clone(&mutex_clone));
                 Some(Arc::clone(&mutex))
             }
         }
     }
 }
 
+impl RpcContext {
+    /// Runs the handler of an incoming request, the calls it makes carry on the
+    /// [`PROPAGATED_KEYS`] of `headers`. Calls made from tasks spawned by the handler
+    /// do not see them.
+    pub(crate) async fn scope<F: Future>(headers: &http::HeaderMap, fut: F) -> F::Output {
+        let attachments = PROPAGATED_KEYS
+            .iter()
+            .filter_map(|key| {
+                let value = headers.get(*key)?.to_str().ok()?;
+                Some((key.to_string(), value.to_string()))
+            })
+            .collect();
+        PROPAGATED_ATTACHMENTS.scope(attachments, fut).await
+    }
+
+    /// Serves a request of the connection with `peer`.
+    pub(crate) async fn peer_scope<F: Future>(peer: Option<PeerIdentity>, fut: F) -> F::Output {
+        PEER_IDENTITY.scope(peer, fut).await
+    }
+
+    /// The identity of the mutual TLS client whose request is being served by the current task,
+    /// it is also in the extensions of the http request.
+    pub fn get_peer_identity() -> Option<PeerIdentity> {
+        PEER_IDENTITY.try_with(|peer| peer.clone()).ok().flatten()
+    }
+
+    /// The propagated attachment of the request being served by the current task.
+    pub fn get_propagated_attachment(key: &str) -> Option<String> {
+        PROPAGATED_ATTACHMENTS
+            .try_with(|attachments| attachments.get(key).cloned())
+            .ok()
+            .flatten()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use tokio::time;
@@ -71,6 +138,17 @@ mod tests {
     use super::*;
     use std::time::Duration;
 
+    #[tokio::test]
+    async fn peer_identity_in_scope() {
+        let peer = PeerIdentity::new(vec![Certificate(vec![1, 2, 3])]);
+        let certs = RpcContext::peer_scope(Some(peer), async {
+            RpcContext::get_peer_identity().map(|peer| peer.certs().to_vec())
+        })
+        .await;
+        assert_eq!(certs, Some(vec![Certificate(vec![1, 2, 3])]));
+        assert!(RpcContext::get_peer_identity().is_none());
+    }
+
     #[test]
     fn context_with_thread_local() {
         let rt = tokio::runtime::Builder::new_multi_thread()
@@ -87,14 +165,14 @@ mod tests {
                     let mut attachments = attachments.lock().unwrap();
                     attachments.insert("key1".into(), Value::from(format!("data-{i}")));
 
-                    assert!(attachments.len() > 0);
+                    assert!(!attachments.is_empty());
                 };
 
                 time::sleep(Duration::from_millis(1000)).await;
 
                 if let Some(attachments) = RpcContext::get_attachments() {
                     let attachments = attachments.lock().unwrap();
-                    assert!(attachments.len() > 0);
+                    assert!(!attachments.is_empty());
                 };
             }));
         }
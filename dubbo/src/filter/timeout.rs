@@ -56,7 +56,7 @@ impl Filter for TimeoutFilter {
                 tri_timeout_deadline_in_nanos,
                 current_nanos
             );
-            if tri_timeout_deadline_in_nanos - current_nanos <= 0 {
+            if tri_timeout_deadline_in_nanos <= current_nanos {
                 return Err(Status::new(Code::DeadlineExceeded, String::from("Timeout")));
             }
         }
@@ -20,6 +20,7 @@ use std::{
     error::Error,
     pin::Pin,
     sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 use crate::{
@@ -30,7 +31,10 @@ use crate::{
         BoxRegistry, Registry,
     },
 };
-use dubbo_base::Url;
+use dubbo_base::{
+    constants::{REGION_KEY, TAG_KEY, TIMESTAMP_KEY, ZONE_KEY},
+    Url,
+};
 use dubbo_config::{get_global_config, protocol::ProtocolRetrieve, RootConfig};
 use dubbo_logger::tracing;
 use futures::{future, Future};
@@ -99,16 +103,26 @@ impl Dubbo {
             if url.is_none() {
                 continue;
             }
-            let u = url.unwrap();
-            if self.protocols.get(&service_config.protocol).is_some() {
-                self.protocols
-                    .get_mut(&service_config.protocol)
-                    .unwrap()
-                    .push(u);
-            } else {
-                self.protocols
-                    .insert(service_config.protocol.clone(), vec![u]);
+            let mut u = url.unwrap();
+            // start time of the provider, consumers ramp up its weight during warmup
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis();
+            u.set_param(TIMESTAMP_KEY, &timestamp.to_string());
+            if !service_config.tag.is_empty() {
+                u.set_param(TAG_KEY, &service_config.tag);
+            }
+            if !root_config.provider.zone.is_empty() {
+                u.set_param(ZONE_KEY, &root_config.provider.zone);
+            }
+            if !root_config.provider.region.is_empty() {
+                u.set_param(REGION_KEY, &root_config.provider.region);
             }
+            self.protocols
+                .entry(service_config.protocol.clone())
+                .or_default()
+                .push(u);
         }
         Ok(())
     }
@@ -129,13 +143,8 @@ impl Dubbo {
                 let exporter = mem_reg.clone().export(url.to_owned());
                 async_vec.push(exporter);
                 //TODO multiple registry
-                if self.registries.is_some() {
-                    self.registries
-                        .as_ref()
-                        .unwrap()
-                        .default_registry()
-                        .register(url.clone())
-                        .unwrap();
+                if let Some(registries) = self.registries.as_ref() {
+                    registries.default_registry().register(url.clone()).unwrap();
                 }
             }
         }
@@ -0,0 +1,231 @@
+// @generated by apache/dubbo-rust.
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct HealthCheckRequest {
+    #[prost(string, tag = "1")]
+    pub service: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct HealthCheckResponse {
+    #[prost(enumeration = "health_check_response::ServingStatus", tag = "1")]
+    pub status: i32,
+}
+/// Nested message and enum types in `HealthCheckResponse`.
+pub mod health_check_response {
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+    #[repr(i32)]
+    pub enum ServingStatus {
+        Unknown = 0,
+        Serving = 1,
+        NotServing = 2,
+        /// Used only by the Watch method.
+        ServiceUnknown = 3,
+    }
+}
+/// Generated client implementations.
+pub mod health_client {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
+    use crate::codegen::*;
+    #[derive(Debug, Clone, Default)]
+    pub struct HealthClient {
+        inner: TripleClient,
+    }
+    impl HealthClient {
+        pub fn connect(host: String) -> Self {
+            let cli = TripleClient::connect(host);
+            HealthClient { inner: cli }
+        }
+        pub fn new(builder: ClientBuilder) -> Self {
+            Self {
+                inner: TripleClient::new(builder),
+            }
+        }
+        pub fn with_cluster(mut self, invoker: ClusterInvoker) -> Self {
+            self.inner = self.inner.with_cluster(invoker);
+            self
+        }
+        /// If the requested service is unknown, the call will fail with status
+        /// NOT_FOUND.
+        pub async fn check(
+            &mut self,
+            request: Request<super::HealthCheckRequest>,
+        ) -> Result<Response<super::HealthCheckResponse>, crate::status::Status> {
+            let codec = crate::codegen::ProstCodec::<
+                super::HealthCheckRequest,
+                super::HealthCheckResponse,
+            >::default();
+            let invocation = RpcInvocation::default()
+                .with_service_unique_name(String::from("grpc.health.v1.Health"))
+                .with_method_name(String::from("Check"));
+            let path = http::uri::PathAndQuery::from_static("/grpc.health.v1.Health/Check");
+            self.inner.unary(request, codec, path, invocation).await
+        }
+        /// Performs a watch for the serving status of the requested service.
+        pub async fn watch(
+            &mut self,
+            request: Request<super::HealthCheckRequest>,
+        ) -> Result<Response<Decoding<super::HealthCheckResponse>>, crate::status::Status> {
+            let codec = crate::codegen::ProstCodec::<
+                super::HealthCheckRequest,
+                super::HealthCheckResponse,
+            >::default();
+            let invocation = RpcInvocation::default()
+                .with_service_unique_name(String::from("grpc.health.v1.Health"))
+                .with_method_name(String::from("Watch"));
+            let path = http::uri::PathAndQuery::from_static("/grpc.health.v1.Health/Watch");
+            self.inner
+                .server_streaming(request, codec, path, invocation)
+                .await
+        }
+    }
+}
+/// Generated server implementations.
+pub mod health_server {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
+    use crate::codegen::*;
+    ///Generated trait containing gRPC methods that should be implemented for use with HealthServer.
+    #[async_trait]
+    pub trait Health: Send + Sync + 'static {
+        /// If the requested service is unknown, the call will fail with status
+        /// NOT_FOUND.
+        async fn check(
+            &self,
+            request: Request<super::HealthCheckRequest>,
+        ) -> Result<Response<super::HealthCheckResponse>, crate::status::Status>;
+        ///Server streaming response type for the Watch method.
+        type WatchStream: futures_util::Stream<Item = Result<super::HealthCheckResponse, crate::status::Status>>
+            + Send
+            + 'static;
+        /// Performs a watch for the serving status of the requested service.
+        async fn watch(
+            &self,
+            request: Request<super::HealthCheckRequest>,
+        ) -> Result<Response<Self::WatchStream>, crate::status::Status>;
+    }
+    #[derive(Debug)]
+    pub struct HealthServer<T: Health> {
+        inner: _Inner<T>,
+    }
+    struct _Inner<T>(Arc<T>);
+    impl<T: Health> HealthServer<T> {
+        pub fn new(inner: T) -> Self {
+            Self {
+                inner: _Inner(Arc::new(inner)),
+            }
+        }
+        pub fn with_filter<F>(inner: T, filter: F) -> FilterService<Self, F>
+        where
+            F: Filter,
+        {
+            FilterService::new(Self::new(inner), filter)
+        }
+    }
+    impl<T, B> Service<http::Request<B>> for HealthServer<T>
+    where
+        T: Health,
+        B: Body + Send + 'static,
+        B::Error: Into<StdError> + Send + 'static,
+    {
+        type Response = http::Response<BoxBody>;
+        type Error = std::convert::Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, req: http::Request<B>) -> Self::Future {
+            let inner = self.inner.clone();
+            match req.uri().path() {
+                "/grpc.health.v1.Health/Check" => {
+                    #[allow(non_camel_case_types)]
+                    struct CheckServer<T: Health> {
+                        inner: _Inner<T>,
+                    }
+                    impl<T: Health> UnarySvc<super::HealthCheckRequest> for CheckServer<T> {
+                        type Response = super::HealthCheckResponse;
+                        type Future = BoxFuture<Response<Self::Response>, crate::status::Status>;
+                        fn call(
+                            &mut self,
+                            request: Request<super::HealthCheckRequest>,
+                        ) -> Self::Future {
+                            let inner = self.inner.0.clone();
+                            let fut = async move { inner.check(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let fut = async move {
+                        let mut server = TripleServer::new(crate::codegen::ProstCodec::<
+                            super::HealthCheckResponse,
+                            super::HealthCheckRequest,
+                        >::default());
+                        let res = server.unary(CheckServer { inner }, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/grpc.health.v1.Health/Watch" => {
+                    #[allow(non_camel_case_types)]
+                    struct WatchServer<T: Health> {
+                        inner: _Inner<T>,
+                    }
+                    impl<T: Health> ServerStreamingSvc<super::HealthCheckRequest> for WatchServer<T> {
+                        type Response = super::HealthCheckResponse;
+                        type ResponseStream = T::WatchStream;
+                        type Future =
+                            BoxFuture<Response<Self::ResponseStream>, crate::status::Status>;
+                        fn call(
+                            &mut self,
+                            request: Request<super::HealthCheckRequest>,
+                        ) -> Self::Future {
+                            let inner = self.inner.0.clone();
+                            let fut = async move { inner.watch(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let fut = async move {
+                        let mut server = TripleServer::new(crate::codegen::ProstCodec::<
+                            super::HealthCheckResponse,
+                            super::HealthCheckRequest,
+                        >::default());
+                        let res = server.server_streaming(WatchServer { inner }, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                _ => Box::pin(async move {
+                    Ok(http::Response::builder()
+                        .status(200)
+                        .header("grpc-status", "12")
+                        .header("content-type", "application/grpc")
+                        .body(empty_body())
+                        .unwrap())
+                }),
+            }
+        }
+    }
+    impl<T: Health> Clone for HealthServer<T> {
+        fn clone(&self) -> Self {
+            let inner = self.inner.clone();
+            Self { inner }
+        }
+    }
+    impl<T: Health> Clone for _Inner<T> {
+        fn clone(&self) -> Self {
+            Self(self.0.clone())
+        }
+    }
+    impl<T: std::fmt::Debug> std::fmt::Debug for _Inner<T> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{:?}", self.0)
+        }
+    }
+    pub fn register_server<T: Health>(server: T) {
+        let s = HealthServer::new(server);
+        crate::protocol::triple::TRIPLE_SERVICES
+            .write()
+            .unwrap()
+            .insert(
+                "grpc.health.v1.Health".to_string(),
+                crate::utils::boxed_clone::BoxCloneService::new(s),
+            );
+    }
+}
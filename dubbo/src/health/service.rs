@@ -0,0 +1,211 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::{
+    collections::HashMap,
+    pin::Pin,
+    sync::{Arc, RwLock},
+};
+
+use async_trait::async_trait;
+use dubbo_logger::tracing;
+use futures_core::Stream;
+use tokio::sync::watch;
+
+use crate::{
+    health::{
+        generated::health_server::Health, HealthCheckRequest, HealthCheckResponse, ServingStatus,
+    },
+    invocation::{Request, Response},
+    status::{Code, Status},
+};
+
+/// Sets the serving status reported by the health service, the empty service name stands for
+/// the overall status of the server.
+#[derive(Debug, Clone, Default)]
+pub struct HealthReporter {
+    inner: Arc<RwLock<ReporterState>>,
+}
+
+#[derive(Debug, Default)]
+struct ReporterState {
+    statuses: HashMap<String, watch::Sender<ServingStatus>>,
+    // once shut down, every service stays NOT_SERVING
+    shutdown: bool,
+}
+
+impl HealthReporter {
+    pub fn new() -> Self {
+        HealthReporter::default()
+    }
+
+    pub fn set_serving(&self, service: &str) {
+        self.set_service_status(service, ServingStatus::Serving);
+    }
+
+    pub fn set_not_serving(&self, service: &str) {
+        self.set_service_status(service, ServingStatus::NotServing);
+    }
+
+    pub fn set_service_status(&self, service: &str, status: ServingStatus) {
+        let mut state = self.inner.write().unwrap();
+        if state.shutdown {
+            tracing::debug!(
+                "server is shutting down, ignore status {:?} of {}",
+                status,
+                service
+            );
+            return;
+        }
+        match state.statuses.get(service) {
+            Some(sender) => {
+                sender.send_replace(status);
+            }
+            None => {
+                let (sender, _) = watch::channel(status);
+                state.statuses.insert(service.to_string(), sender);
+            }
+        }
+    }
+
+    /// The status of `service`, `None` if it has never been set.
+    pub fn get_service_status(&self, service: &str) -> Option<ServingStatus> {
+        let state = self.inner.read().unwrap();
+        let status = *state.statuses.get(service)?.borrow();
+        match status {
+            ServingStatus::ServiceUnknown => None,
+            status => Some(status),
+        }
+    }
+
+    /// Accepts the statuses again after a shutdown, called when the server starts again.
+    pub fn resume(&self) {
+        self.inner.write().unwrap().shutdown = false;
+    }
+
+    /// Marks every service NOT_SERVING until resumed, called when the server stops.
+    pub fn shutdown(&self) {
+        let mut state = self.inner.write().unwrap();
+        state.shutdown = true;
+        for sender in state.statuses.values() {
+            sender.send_replace(ServingStatus::NotServing);
+        }
+    }
+
+    fn subscribe(&self, service: &str) -> watch::Receiver<ServingStatus> {
+        let mut state = self.inner.write().unwrap();
+        let status = if state.shutdown {
+            ServingStatus::NotServing
+        } else {
+            ServingStatus::ServiceUnknown
+        };
+        state
+            .statuses
+            .entry(service.to_string())
+            .or_insert_with(|| watch::channel(status).0)
+            .subscribe()
+    }
+}
+
+/// The `grpc.health.v1.Health` service backed by a reporter.
+#[derive(Debug, Clone)]
+pub struct HealthService {
+    reporter: HealthReporter,
+}
+
+impl HealthService {
+    pub fn new(reporter: HealthReporter) -> Self {
+        HealthService { reporter }
+    }
+}
+
+#[async_trait]
+impl Health for HealthService {
+    async fn check(
+        &self,
+        request: Request<HealthCheckRequest>,
+    ) -> Result<Response<HealthCheckResponse>, Status> {
+        let service = request.into_inner().service;
+        match self.reporter.get_service_status(&service) {
+            Some(status) => Ok(Response::new(HealthCheckResponse {
+                status: status as i32,
+            })),
+            None => Err(Status::new(
+                Code::NotFound,
+                format!("unknown service {}", service),
+            )),
+        }
+    }
+
+    type WatchStream =
+        Pin<Box<dyn Stream<Item = Result<HealthCheckResponse, Status>> + Send + 'static>>;
+
+    async fn watch(
+        &self,
+        request: Request<HealthCheckRequest>,
+    ) -> Result<Response<Self::WatchStream>, Status> {
+        let service = request.into_inner().service;
+        let mut receiver = self.reporter.subscribe(&service);
+        let stream = async_stream::stream! {
+            loop {
+                let status = *receiver.borrow_and_update();
+                yield Ok(HealthCheckResponse {
+                    status: status as i32,
+                });
+                if receiver.changed().await.is_err() {
+                    break;
+                }
+            }
+        };
+        Ok(Response::new(Box::pin(stream) as Self::WatchStream))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shutdown_reports_not_serving() {
+        let reporter = HealthReporter::new();
+        reporter.set_serving("");
+        reporter.set_serving("helloworld.Greeter");
+        assert_eq!(
+            reporter.get_service_status("helloworld.Greeter"),
+            Some(ServingStatus::Serving)
+        );
+        assert_eq!(reporter.get_service_status("unknown.Service"), None);
+
+        reporter.shutdown();
+        reporter.set_serving("helloworld.Greeter");
+        assert_eq!(
+            reporter.get_service_status(""),
+            Some(ServingStatus::NotServing)
+        );
+        assert_eq!(
+            reporter.get_service_status("helloworld.Greeter"),
+            Some(ServingStatus::NotServing)
+        );
+
+        reporter.resume();
+        reporter.set_serving("helloworld.Greeter");
+        assert_eq!(
+            reporter.get_service_status("helloworld.Greeter"),
+            Some(ServingStatus::Serving)
+        );
+    }
+}
@@ -178,6 +178,14 @@ impl Metadata {
         Metadata { inner: h }
     }
 
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.inner.get(key).map(|v| v.as_str())
+    }
+
+    pub fn insert(&mut self, key: String, value: String) -> Option<String> {
+        self.inner.insert(key, value)
+    }
+
     pub fn into_headers(&self) -> http::HeaderMap {
         let mut header = http::HeaderMap::new();
         for (k, v) in self.inner.clone().into_iter() {
@@ -200,6 +208,10 @@ pub trait Invocation {
 pub struct RpcInvocation {
     target_service_unique_name: String,
     method_name: String,
+    // request metadata of the call
+    attachments: HashMap<String, String>,
+    // encoded request message, only present for calls with a single request message
+    arguments: Option<bytes::Bytes>,
 }
 
 impl RpcInvocation {
@@ -211,6 +223,27 @@ impl RpcInvocation {
         self.method_name = method_name;
         self
     }
+    pub fn with_metadata(mut self, metadata: &Metadata) -> Self {
+        self.attachments = metadata.inner.clone();
+        self
+    }
+    pub fn with_attachment(mut self, key: String, value: String) -> Self {
+        self.attachments.insert(key, value);
+        self
+    }
+    pub fn with_arguments(mut self, arguments: bytes::Bytes) -> Self {
+        self.arguments = Some(arguments);
+        self
+    }
+    pub fn get_attachment(&self, key: &str) -> Option<&str> {
+        self.attachments.get(key).map(|v| v.as_str())
+    }
+    pub fn get_attachments(&self) -> &HashMap<String, String> {
+        &self.attachments
+    }
+    pub fn get_arguments(&self) -> Option<&bytes::Bytes> {
+        self.arguments.as_ref()
+    }
     pub fn unique_fingerprint(&self) -> String {
         format!("{}#{}", self.target_service_unique_name, self.method_name)
     }
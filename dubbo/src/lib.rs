@@ -19,6 +19,7 @@ pub mod cluster;
 pub mod codegen;
 pub mod context;
 pub mod filter;
+pub mod health;
 mod framework;
 pub mod invocation;
 pub mod protocol;
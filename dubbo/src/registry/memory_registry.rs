@@ -17,10 +17,11 @@
 
 #![allow(unused_variables, dead_code, missing_docs)]
 
-use dubbo_logger::tracing::debug;
+use arc_swap::ArcSwap;
+use dubbo_logger::tracing::{debug, warn};
 use std::{
     collections::HashMap,
-    sync::{Arc, RwLock},
+    sync::{Arc, Mutex, RwLock},
 };
 
 use dubbo_base::Url;
@@ -28,8 +29,8 @@ use dubbo_base::Url;
 use super::{NotifyListener, Registry, RegistryNotifyListener};
 
 // 从url中获取服务注册的元数据
-/// rawURL = fmt.Sprintf("%s://%s%s?%s", c.Protocol, host, c.Path, s)
-/// dubboPath = fmt.Sprintf("/%s/%s/%s", r.URL.GetParam(constant.RegistryGroupKey, "dubbo"), r.service(c), common.DubboNodes[common.PROVIDER])
+// rawURL = fmt.Sprintf("%s://%s%s?%s", c.Protocol, host, c.Path, s)
+// dubboPath = fmt.Sprintf("/%s/%s/%s", r.URL.GetParam(constant.RegistryGroupKey, "dubbo"), r.service(c), common.DubboNodes[common.PROVIDER])
 
 pub const REGISTRY_GROUP_KEY: &str = "registry.group";
 
@@ -103,21 +104,35 @@ impl Registry for MemoryRegistry {
     }
 }
 
+/// The providers of each service, replaced as a whole so that readers never wait.
+pub type ServiceInstances = Arc<ArcSwap<HashMap<String, Arc<Vec<Url>>>>>;
+
+/// Keeps the providers of the subscribed services up to date with the registry events.
 pub struct MemoryNotifyListener {
-    pub service_instances: Arc<RwLock<HashMap<String, Vec<Url>>>>,
+    pub service_instances: ServiceInstances,
+    // serializes the updates of `service_instances`
+    pub updating: Arc<Mutex<()>>,
 }
 
 impl NotifyListener for MemoryNotifyListener {
     fn notify(&self, event: super::ServiceEvent) {
         debug!("notify {:?}", event);
-        let mut map = self.service_instances.write().expect("msg");
-        match event.action.as_str() {
-            "ADD" => map.insert(event.key, event.service),
-            &_ => todo!(),
+        let _updating = self.updating.lock().unwrap();
+        let current = self.service_instances.load();
+        let providers = current.get(&event.key).map(|v| v.as_slice()).unwrap_or(&[]);
+        let providers = match event.apply(providers) {
+            Some(v) => v,
+            None => {
+                warn!("unknown action {} of service {}", event.action, event.key);
+                return;
+            }
         };
+        let mut map = HashMap::clone(&current);
+        map.insert(event.key, Arc::new(providers));
+        self.service_instances.store(Arc::new(map));
     }
 
     fn notify_all(&self, event: super::ServiceEvent) {
-        todo!()
+        self.notify(event)
     }
 }
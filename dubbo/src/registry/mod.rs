@@ -22,11 +22,14 @@ pub mod protocol;
 pub mod types;
 
 use std::{
+    collections::HashMap,
     fmt::{Debug, Formatter},
-    sync::Arc,
+    sync::{Arc, RwLock},
 };
 
 use dubbo_base::Url;
+use dubbo_config::registry::RegistryConfig;
+use lazy_static::lazy_static;
 
 pub type RegistryNotifyListener = Arc<dyn NotifyListener + Send + Sync + 'static>;
 pub trait Registry {
@@ -46,15 +49,92 @@ pub trait NotifyListener {
     fn notify_all(&self, event: ServiceEvent);
 }
 
+/// The providers of the event are added, or replace the ones of the same address.
+pub const ADD_ACTION: &str = "ADD";
+/// The providers of the event replace the ones of the same address.
+pub const UPDATE_ACTION: &str = "UPDATE";
+/// The providers of the event are removed.
+pub const REMOVE_ACTION: &str = "REMOVE";
+/// The providers of the event are all of the providers of the service.
+pub const CHANGE_ACTION: &str = "CHANGE";
+
 #[derive(Debug)]
 pub struct ServiceEvent {
+    // service name
     pub key: String,
+    // one of the `*_ACTION`s
     pub action: String,
     pub service: Vec<Url>,
 }
 
+impl ServiceEvent {
+    /// Applies the event to the current `providers` of the service, `None` for an unknown action.
+    pub fn apply(&self, providers: &[Url]) -> Option<Vec<Url>> {
+        let contains = |url: &Url| {
+            self.service
+                .iter()
+                .any(|u| u.get_ip_port() == url.get_ip_port())
+        };
+        let mut result: Vec<Url> = match self.action.as_str() {
+            CHANGE_ACTION => return Some(self.service.clone()),
+            ADD_ACTION | UPDATE_ACTION | REMOVE_ACTION => providers
+                .iter()
+                .filter(|url| !contains(url))
+                .cloned()
+                .collect(),
+            _ => return None,
+        };
+        match self.action.as_str() {
+            ADD_ACTION => result.extend(self.service.iter().cloned()),
+            UPDATE_ACTION => {
+                for url in self.service.iter() {
+                    if providers
+                        .iter()
+                        .any(|u| u.get_ip_port() == url.get_ip_port())
+                    {
+                        result.push(url.clone());
+                    }
+                }
+            }
+            _ => {}
+        }
+        Some(result)
+    }
+}
+
 pub type BoxRegistry = Box<dyn Registry + Send + Sync>;
 
+/// Creates the registry of a `registries` entry of the config.
+pub type RegistryFactory = fn(&RegistryConfig) -> Result<BoxRegistry, crate::StdError>;
+
+lazy_static! {
+    static ref REGISTRY_FACTORIES: RwLock<HashMap<String, RegistryFactory>> =
+        RwLock::new(HashMap::new());
+}
+
+/// Registers the factory of the registries of `protocol`, eg.
+///
+/// ```ignore
+/// register_registry_factory("zookeeper", |config| {
+///     Ok(Box::new(ZookeeperRegistry::new(&config.address)))
+/// });
+/// ```
+pub fn register_registry_factory(protocol: &str, factory: RegistryFactory) {
+    REGISTRY_FACTORIES
+        .write()
+        .unwrap()
+        .insert(protocol.to_string(), factory);
+}
+
+/// Creates the registry of `config` by the factory of its protocol.
+pub fn create_registry(config: &RegistryConfig) -> Result<BoxRegistry, crate::StdError> {
+    let factory = match REGISTRY_FACTORIES.read().unwrap().get(&config.protocol) {
+        Some(v) => *v,
+        None => return Err(format!("no factory of registry protocol {}", config.protocol).into()),
+    };
+    factory(config)
+}
+
 impl Debug for BoxRegistry {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.write_str("BoxRegistry")
@@ -63,7 +143,7 @@ impl Debug for BoxRegistry {
 
 #[derive(Default)]
 pub struct RegistryWrapper {
-    pub registry: Option<Box<dyn Registry>>,
+    pub registry: Option<BoxRegistry>,
 }
 
 impl Clone for RegistryWrapper {
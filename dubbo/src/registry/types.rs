@@ -75,17 +75,14 @@ impl Registry for SafeRegistry {
     }
 
     fn unregister(&mut self, url: Url) -> Result<(), StdError> {
-        self.lock().unwrap().register(url).expect("registry err.");
-        Ok(())
+        self.lock().unwrap().unregister(url)
     }
 
     fn subscribe(&self, url: Url, listener: RegistryNotifyListener) -> Result<(), StdError> {
-        self.lock().unwrap().register(url).expect("registry err.");
-        Ok(())
+        self.lock().unwrap().subscribe(url, listener)
     }
 
     fn unsubscribe(&self, url: Url, listener: RegistryNotifyListener) -> Result<(), StdError> {
-        self.lock().unwrap().register(url).expect("registry err.");
-        Ok(())
+        self.lock().unwrap().unsubscribe(url, listener)
     }
 }
@@ -274,13 +274,36 @@ impl Status {
     }
 
     pub fn from_error(err: crate::Error) -> Self {
-        Status::new(Code::Internal, err.to_string())
+        match err.downcast::<Status>() {
+            Ok(status) => *status,
+            Err(err) => Status::new(Code::Internal, err.to_string()),
+        }
+    }
+
+    /// Parses the status of a trailers-only response, `None` when `grpc-status` is absent.
+    pub fn from_header_map(headers: &http::HeaderMap) -> Option<Self> {
+        let code = headers
+            .get(GRPC_STATUS)?
+            .to_str()
+            .ok()?
+            .parse::<i32>()
+            .ok()?;
+        let message = headers
+            .get(GRPC_MESSAGE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        Some(Status::new(Code::from_i32(code), message))
     }
 
     pub fn code(&self) -> Code {
         self.code
     }
 
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
     pub fn to_http(&self) -> http::Response<crate::BoxBody> {
         let (mut parts, _) = http::Response::new(()).into_parts();
 
@@ -15,14 +15,37 @@
  * limitations under the License.
  */
 
+use std::{path::PathBuf, sync::Arc};
+
 use crate::{
-    cluster::directory::StaticDirectory,
+    cluster::{
+        directory::{MultiRegistryDirectory, StaticDirectory},
+        router::{
+            condition::ConditionRouter,
+            tag::TagRouter,
+            traffic::{TrafficRouter, WATCH_INTERVAL},
+            zone::ZoneRouter,
+            BoxRouter,
+        },
+        support::{check_url, mock::Fallback},
+    },
     codegen::{ClusterInvoker, Directory, RegistryDirectory},
-    triple::compression::CompressionEncoding,
+    triple::{
+        compression::CompressionEncoding,
+        transport::{connector::tls_connector::TlsConnector, pool::ConnectionPool},
+    },
     utils::boxed::BoxService,
 };
 
 use aws_smithy_http::body::SdkBody;
+use dubbo_base::{
+    constants::{CLUSTER_KEY, CONNECTIONS_KEY, LOADBALANCE_KEY, MOCK_KEY, REGION_KEY, ZONE_KEY},
+    Url,
+};
+use dubbo_config::{
+    get_global_config, protocol::DEFAULT_PROTOCOL, reference::ReferenceConfig,
+    router::TrafficConfig, tls::TlsConfig,
+};
 
 use super::TripleClient;
 
@@ -35,6 +58,10 @@ pub struct ClientBuilder {
     pub connector: &'static str,
     directory: Option<Box<dyn Directory>>,
     cluster_invoker: Option<ClusterInvoker>,
+    url: Url,
+    routers: Vec<BoxRouter>,
+    fallback: Option<Fallback>,
+    tls: Option<TlsConnector>,
 }
 
 impl ClientBuilder {
@@ -44,6 +71,10 @@ impl ClientBuilder {
             connector: "",
             directory: None,
             cluster_invoker: None,
+            url: Url::default(),
+            routers: Vec::new(),
+            fallback: None,
+            tls: None,
         }
     }
 
@@ -51,8 +82,12 @@ impl ClientBuilder {
         Self {
             timeout: None,
             connector: "",
-            directory: Some(Box::new(StaticDirectory::new(&host))),
+            directory: Some(Box::new(StaticDirectory::new(host))),
             cluster_invoker: None,
+            url: Url::default(),
+            routers: Vec::new(),
+            fallback: None,
+            tls: None,
         }
     }
 
@@ -60,8 +95,12 @@ impl ClientBuilder {
         Self {
             timeout: None,
             connector: "",
-            directory: Some(Box::new(StaticDirectory::from_uri(&uri))),
+            directory: Some(Box::new(StaticDirectory::from_uri(uri))),
             cluster_invoker: None,
+            url: Url::default(),
+            routers: Vec::new(),
+            fallback: None,
+            tls: None,
         }
     }
 
@@ -84,31 +123,223 @@ impl ClientBuilder {
     pub fn with_registry_directory(self, registry: RegistryDirectory) -> Self {
         Self {
             directory: None,
-            cluster_invoker: Some(ClusterInvoker::with_directory(registry)),
+            cluster_invoker: Some(ClusterInvoker::with_registry_directory(registry)),
             ..self
         }
     }
 
     pub fn with_host(self, host: &'static str) -> Self {
         Self {
-            directory: Some(Box::new(StaticDirectory::new(&host))),
+            directory: Some(Box::new(StaticDirectory::new(host))),
             ..self
         }
     }
 
+    /// reference url, its params configure the cluster, eg. `retries`. Fails when the url
+    /// names an unknown cluster or loadbalance, see [`ClusterInvoker::with_url`].
+    pub fn with_url(self, url: Url) -> Result<Self, crate::StdError> {
+        check_url(&url).map_err(|err| format!("invalid url of {}: {}", url.service_name, err))?;
+        Ok(Self { url, ..self })
+    }
+
+    /// Appends a router of the providers, the routers run in the order they are added and
+    /// before the zone and tag routers, which always come last.
+    pub fn with_router(mut self, router: BoxRouter) -> Self {
+        self.routers.push(router);
+        self
+    }
+
+    /// Degrades the calls failing with `Unavailable` or `DeadlineExceeded` by `fallback`,
+    /// the `mock` param of a method overrides it.
+    pub fn with_fallback(self, fallback: Fallback) -> Self {
+        Self {
+            fallback: Some(fallback),
+            ..self
+        }
+    }
+
+    /// Connects to the providers by TLS, eg.
+    /// `ClientTlsConfig::new().with_ca_certs(certs).connector()?`.
+    pub fn with_tls(self, tls: TlsConnector) -> Self {
+        Self {
+            tls: Some(tls),
+            ..self
+        }
+    }
+
+    /// Configures the client by the reference `name` of `consumer.references` in global config,
+    /// the providers of a reference without `url` are found in the `registries`. The reference
+    /// connects by the tls of its protocol unless it has its own.
+    pub fn from_reference(name: &str) -> Result<ClientBuilder, crate::StdError> {
+        let config = get_global_config();
+        let consumer = &config.consumer;
+        let reference = match consumer.references.get(name) {
+            Some(v) => v,
+            None => return Err(format!("reference {} not found", name).into()),
+        };
+        let mut builder = ClientBuilder::new();
+        if reference.url.is_empty() && !config.registries.is_empty() {
+            let directory = MultiRegistryDirectory::from_config(&config.registries)?;
+            builder = builder.with_directory(Box::new(directory));
+        }
+        let protocol = match reference.protocol.as_str() {
+            "" => DEFAULT_PROTOCOL,
+            name => name,
+        };
+        let tls = config
+            .protocols
+            .get(protocol)
+            .and_then(|protocol| protocol.tls.as_ref());
+        if let (None, Some(tls)) = (reference.tls.as_ref(), tls) {
+            builder = builder.with_tls(tls_connector(tls, &reference.interface)?);
+        }
+        let mut builder = builder.with_reference(reference)?;
+        for (key, value) in [(ZONE_KEY, &consumer.zone), (REGION_KEY, &consumer.region)] {
+            if !value.is_empty() && builder.url.get_param(key).is_none() {
+                builder.url.set_param(key, value);
+            }
+        }
+        Ok(builder)
+    }
+
+    /// Configures the cluster by the reference config, a reference with `url` connects to the
+    /// provider directly unless a directory is given.
+    pub fn with_reference(self, reference: &ReferenceConfig) -> Result<Self, crate::StdError> {
+        let url = reference_url(reference)?;
+        let mut routers = self.routers;
+        for rule in reference.router.conditions.iter() {
+            let router = ConditionRouter::parse(rule)
+                .map_err(|err| format!("invalid reference {}: {}", reference.interface, err))?
+                .with_force(reference.router.force);
+            routers.push(Arc::new(router));
+        }
+        let invalid_traffic =
+            |err: crate::StdError| format!("invalid reference {}: {}", reference.interface, err);
+        if !reference.router.traffic_file.is_empty() {
+            let path = PathBuf::from(&reference.router.traffic_file);
+            let router = TrafficConfig::load(&path)
+                .map_err(crate::StdError::from)
+                .and_then(|config| TrafficRouter::new(&config))
+                .map_err(invalid_traffic)?;
+            let router = Arc::new(router);
+            router.watch(path, WATCH_INTERVAL);
+            routers.push(router);
+        } else if let Some(traffic) = reference.router.traffic.as_ref() {
+            let router = TrafficRouter::new(traffic).map_err(invalid_traffic)?;
+            routers.push(Arc::new(router));
+        }
+        let tls = match reference.tls.as_ref() {
+            Some(tls) => Some(tls_connector(tls, &reference.interface)?),
+            None => self.tls,
+        };
+        let directory = match self.directory {
+            None if self.cluster_invoker.is_none() && !reference.url.is_empty() => {
+                let directory = StaticDirectory::from_endpoints(reference.url.split(','))
+                    .map_err(|err| format!("invalid reference {}: {}", reference.interface, err))?;
+                Some(Box::new(directory) as Box<dyn Directory>)
+            }
+            directory => directory,
+        };
+        Ok(Self {
+            directory,
+            url,
+            routers,
+            tls,
+            ..self
+        })
+    }
+
     pub fn with_connector(self, connector: &'static str) -> Self {
         Self {
-            connector: connector,
+            connector,
             cluster_invoker: None,
             ..self
         }
     }
 
     pub fn build(self) -> TripleClient {
+        let cluster_invoker = match (self.cluster_invoker, self.directory) {
+            (Some(invoker), _) => Some(invoker),
+            (None, Some(directory)) => Some(ClusterInvoker::with_directory(directory)),
+            (None, None) => None,
+        };
+        let cluster_invoker = match (cluster_invoker, self.fallback) {
+            (Some(invoker), Some(fallback)) => Some(invoker.with_fallback(fallback)),
+            (invoker, _) => invoker,
+        };
+        let cluster_invoker = match (cluster_invoker, self.tls) {
+            (Some(invoker), Some(tls)) => {
+                Some(invoker.with_pool(ConnectionPool::new("http").with_tls(tls)))
+            }
+            (invoker, _) => invoker,
+        };
+        let url = self.url;
+        let mut routers = self.routers;
+        routers.push(Arc::new(ZoneRouter::new()));
+        routers.push(Arc::new(TagRouter::new()));
         TripleClient {
             send_compression_encoding: Some(CompressionEncoding::Gzip),
-            directory: self.directory,
-            cluster_invoker: self.cluster_invoker,
+            cluster: cluster_invoker.map(|invoker| {
+                routers
+                    .into_iter()
+                    .fold(invoker.with_checked_url(url), |invoker, router| {
+                        invoker.with_router(router)
+                    })
+                    .join()
+            }),
         }
     }
 }
+
+fn tls_connector(tls: &TlsConfig, interface: &str) -> Result<TlsConnector, crate::StdError> {
+    TlsConnector::from_config(tls)
+        .map_err(|err| format!("invalid tls of reference {}: {}", interface, err).into())
+}
+
+fn reference_url(reference: &ReferenceConfig) -> Result<Url, crate::StdError> {
+    let mut url = Url::new();
+    url.scheme = if reference.protocol.is_empty() {
+        "tri".to_string()
+    } else {
+        reference.protocol.clone()
+    };
+    url.service_name = reference.interface.clone();
+    if let Some(endpoint) = reference.url.split(',').find(|v| !v.trim().is_empty()) {
+        let uri = endpoint.trim().parse::<http::Uri>()?;
+        url.ip = uri.host().unwrap_or_default().to_string();
+        url.port = uri.port().map(|p| p.to_string()).unwrap_or_default();
+        url.location = url.get_ip_port();
+    }
+    for (k, v) in reference.params.iter() {
+        url.set_param(k, v);
+    }
+    if !reference.cluster.is_empty() {
+        url.set_param(CLUSTER_KEY, &reference.cluster);
+    }
+    if !reference.loadbalance.is_empty() {
+        url.set_param(LOADBALANCE_KEY, &reference.loadbalance);
+    }
+    if !reference.mock.is_empty() {
+        url.set_param(MOCK_KEY, &reference.mock);
+    }
+    if reference.connections > 0 {
+        url.set_param(CONNECTIONS_KEY, &reference.connections.to_string());
+    }
+    for (method, config) in reference.methods.iter() {
+        for (k, v) in config.params.iter() {
+            url.set_param(&format!("{}.{}", method, k), v);
+        }
+        if !config.loadbalance.is_empty() {
+            url.set_param(
+                &format!("{}.{}", method, LOADBALANCE_KEY),
+                &config.loadbalance,
+            );
+        }
+        if !config.mock.is_empty() {
+            url.set_param(&format!("{}.{}", method, MOCK_KEY), &config.mock);
+        }
+    }
+
+    check_url(&url).map_err(|err| format!("invalid reference {}: {}", reference.interface, err))?;
+    Ok(url)
+}
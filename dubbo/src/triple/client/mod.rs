@@ -16,6 +16,7 @@
  */
 
 pub mod builder;
+pub mod rpc_status;
 pub mod triple;
 
 pub use triple::TripleClient;
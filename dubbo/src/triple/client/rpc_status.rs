@@ -0,0 +1,324 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::{
+    collections::HashMap,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use dubbo_base::Url;
+use futures_util::ready;
+use http_body::Body;
+use lazy_static::lazy_static;
+use pin_project::pin_project;
+
+use crate::{
+    cluster::outlier::is_provider_failure,
+    status::{Code, Status},
+};
+
+lazy_static! {
+    static ref METHOD_STATISTICS: RwLock<HashMap<String, Arc<RpcStatus>>> =
+        RwLock::new(HashMap::new());
+}
+
+// decay time of the latency ewma, a sample weighs half after about 7 seconds
+const EWMA_DECAY: Duration = Duration::from_secs(10);
+
+// latency counted for a call failed by the provider, so that a provider failing fast is not
+// preferred
+pub(crate) const FAILURE_PENALTY: Duration = Duration::from_secs(1);
+
+/// Statistics of the calls from this process to a provider method, collected by the client
+/// call path and read by the loadbalance policies.
+#[derive(Debug, Default)]
+pub struct RpcStatus {
+    active: AtomicUsize,
+    total: AtomicU64,
+    failed: AtomicU64,
+    // in microseconds
+    succeeded_elapsed: AtomicU64,
+    ewma: Mutex<Ewma>,
+}
+
+#[derive(Debug, Default)]
+struct Ewma {
+    // in microseconds
+    value: f64,
+    updated: Option<Instant>,
+}
+
+impl RpcStatus {
+    /// Returns the statistics of `method` on the provider of `url`.
+    pub fn get(url: &Url, method: &str) -> Arc<RpcStatus> {
+        let key = format!("{}#{}", url.short_url(), method);
+        if let Some(status) = METHOD_STATISTICS.read().unwrap().get(&key) {
+            return status.clone();
+        }
+        METHOD_STATISTICS
+            .write()
+            .unwrap()
+            .entry(key)
+            .or_default()
+            .clone()
+    }
+
+    /// Marks the start of a call, the call ends when the returned guard is ended or dropped.
+    pub(crate) fn begin_count(url: &Url, method: &str) -> RpcCall {
+        let status = Self::get(url, method);
+        status.active.fetch_add(1, Ordering::SeqCst);
+        RpcCall {
+            status,
+            start: Instant::now(),
+            latency: None,
+            listener: None,
+            ended: false,
+        }
+    }
+
+    /// The number of in-flight calls, a streaming call is in flight until its response ends.
+    pub fn active(&self) -> usize {
+        self.active.load(Ordering::SeqCst)
+    }
+
+    pub fn total(&self) -> u64 {
+        self.total.load(Ordering::SeqCst)
+    }
+
+    /// The calls failed by the provider, the errors of the application, eg. `NotFound`, are
+    /// not counted.
+    pub fn failed(&self) -> u64 {
+        self.failed.load(Ordering::SeqCst)
+    }
+
+    pub fn succeeded(&self) -> u64 {
+        self.total().saturating_sub(self.failed())
+    }
+
+    /// The sum of the latency of the calls not failed by the provider.
+    pub fn succeeded_elapsed(&self) -> Duration {
+        Duration::from_micros(self.succeeded_elapsed.load(Ordering::SeqCst))
+    }
+
+    /// The exponentially weighted moving average of the call latency, calls failed by the
+    /// provider count as at least one second.
+    pub fn ewma_latency(&self) -> Duration {
+        Duration::from_micros(self.ewma.lock().unwrap().value as u64)
+    }
+
+    fn observe(&self, latency: Duration) {
+        let now = Instant::now();
+        let sample = latency.as_micros() as f64;
+        let mut ewma = self.ewma.lock().unwrap();
+        ewma.value = match ewma.updated {
+            Some(updated) => {
+                let w =
+                    (-now.duration_since(updated).as_secs_f64() / EWMA_DECAY.as_secs_f64()).exp();
+                ewma.value * w + sample * (1.0 - w)
+            }
+            None => sample,
+        };
+        ewma.updated = Some(now);
+    }
+}
+
+/// An in-flight call, dropping it without `end` counts it as a call cancelled by the caller,
+/// which is left out of the statistics.
+///
+/// The latency of a call is the time until its response headers arrive, so that long running
+/// streams do not count as slow calls.
+pub(crate) struct RpcCall {
+    status: Arc<RpcStatus>,
+    start: Instant,
+    latency: Option<Duration>,
+    listener: Option<CallListener>,
+    ended: bool,
+}
+
+/// Notified with the status code of a call when it ends, but not when it is cancelled.
+pub(crate) type CallListener = Box<dyn FnOnce(Code) + Send>;
+
+impl RpcCall {
+    pub(crate) fn responded(&mut self) {
+        self.latency = Some(self.start.elapsed());
+    }
+
+    pub(crate) fn end(mut self, code: Code) {
+        self.finish(Some(code));
+    }
+
+    fn finish(&mut self, code: Option<Code>) {
+        if self.ended {
+            return;
+        }
+        self.ended = true;
+        self.status.active.fetch_sub(1, Ordering::SeqCst);
+        let code = match code {
+            Some(v) => v,
+            None => return,
+        };
+        if let Some(listener) = self.listener.take() {
+            listener(code);
+        }
+        let failed = is_provider_failure(code);
+        let latency = self.latency.unwrap_or_else(|| self.start.elapsed());
+        self.status.total.fetch_add(1, Ordering::SeqCst);
+        if !failed {
+            self.status
+                .succeeded_elapsed
+                .fetch_add(latency.as_micros() as u64, Ordering::SeqCst);
+            self.status.observe(latency);
+        } else {
+            self.status.failed.fetch_add(1, Ordering::SeqCst);
+            self.status.observe(latency.max(FAILURE_PENALTY));
+        }
+    }
+}
+
+impl Drop for RpcCall {
+    fn drop(&mut self) {
+        self.finish(None);
+    }
+}
+
+/// Tracks the call of `method` on the provider of `url` until the response body ends.
+pub(crate) fn track(
+    url: &Url,
+    method: &str,
+    listener: Option<CallListener>,
+    fut: crate::BoxFuture<http::Response<crate::BoxBody>, crate::Error>,
+) -> crate::BoxFuture<http::Response<crate::BoxBody>, crate::Error> {
+    let mut call = RpcStatus::begin_count(url, method);
+    call.listener = listener;
+    Box::pin(async move {
+        match fut.await {
+            Ok(resp) => {
+                call.responded();
+                match Status::from_header_map(resp.headers()) {
+                    // trailers-only response, the call is over already
+                    Some(status) => {
+                        call.end(status.code());
+                        Ok(resp)
+                    }
+                    None => Ok(resp.map(|body| RpcStatusBody::new(body, call).boxed_unsync())),
+                }
+            }
+            Err(err) => {
+                // the provider is unreachable
+                call.end(Code::Unavailable);
+                Err(err)
+            }
+        }
+    })
+}
+
+/// Response body which ends the tracked call with its stream.
+#[pin_project]
+struct RpcStatusBody<B> {
+    #[pin]
+    inner: B,
+    call: Option<RpcCall>,
+}
+
+impl<B> RpcStatusBody<B> {
+    fn new(inner: B, call: RpcCall) -> Self {
+        Self {
+            inner,
+            call: Some(call),
+        }
+    }
+}
+
+impl<B> Body for RpcStatusBody<B>
+where
+    B: Body<Error = Status>,
+{
+    type Data = B::Data;
+
+    type Error = Status;
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let this = self.project();
+        let res = ready!(this.inner.poll_data(cx));
+        if let Some(Err(status)) = &res {
+            if let Some(call) = this.call.take() {
+                call.end(status.code());
+            }
+        }
+        Poll::Ready(res)
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<http::HeaderMap>, Self::Error>> {
+        let this = self.project();
+        let res = ready!(this.inner.poll_trailers(cx));
+        if let Some(call) = this.call.take() {
+            let code = match &res {
+                Ok(Some(trailers)) => Status::from_header_map(trailers)
+                    .map(|status| status.code())
+                    .unwrap_or(Code::Ok),
+                Ok(None) => Code::Ok,
+                Err(status) => status.code(),
+            };
+            call.end(code);
+        }
+        Poll::Ready(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn count_active_calls() {
+        let url = Url::from_url("tri://10.0.7.1:20000/test.RpcStatus").unwrap();
+        let status = RpcStatus::get(&url, "count");
+        let first = RpcStatus::begin_count(&url, "count");
+        let second = RpcStatus::begin_count(&url, "count");
+        assert_eq!(status.active(), 2);
+        first.end(Code::Ok);
+        assert_eq!(status.active(), 1);
+        assert_eq!((status.total(), status.failed()), (1, 0));
+        // a cancelled call is left out of the statistics
+        drop(second);
+        assert_eq!(status.active(), 0);
+        assert_eq!(status.total(), 1);
+        RpcStatus::begin_count(&url, "count").end(Code::Internal);
+        assert_eq!(status.active(), 0);
+        assert_eq!((status.total(), status.failed()), (2, 1));
+        // an application error is not a failure of the provider
+        RpcStatus::begin_count(&url, "count").end(Code::NotFound);
+        assert_eq!((status.total(), status.failed()), (3, 1));
+        assert!(status.ewma_latency() < FAILURE_PENALTY);
+    }
+}
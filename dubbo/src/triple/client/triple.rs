@@ -15,20 +15,20 @@
  * limitations under the License.
  */
 
-use std::{str::FromStr, sync::Arc};
+use std::sync::Arc;
 
 use futures_util::{future, stream, StreamExt, TryStreamExt};
 
 use aws_smithy_http::body::SdkBody;
 use http::HeaderValue;
-use rand::prelude::SliceRandom;
-use tower_service::Service;
+use tower::ServiceExt;
 
-use super::{super::transport::connection::Connection, builder::ClientBuilder};
-use crate::codegen::{ClusterInvoker, Directory, RpcInvocation};
+use super::builder::ClientBuilder;
+use crate::codegen::{ClusterInvoker, RpcInvocation};
 
 use crate::{
-    cluster::support::cluster_invoker::ClusterRequestBuilder,
+    cluster::ClusterBoxService,
+    context::{RpcContext, PROPAGATED_KEYS},
     invocation::{IntoStreamingRequest, Metadata, Request, Response},
     triple::{codec::Codec, compression::CompressionEncoding, decode::Decoding, encode::encode},
 };
@@ -36,8 +36,7 @@ use crate::{
 #[derive(Debug, Clone, Default)]
 pub struct TripleClient {
     pub(crate) send_compression_encoding: Option<CompressionEncoding>,
-    pub(crate) directory: Option<Box<dyn Directory>>,
-    pub(crate) cluster_invoker: Option<ClusterInvoker>,
+    pub(crate) cluster: Option<ClusterBoxService>,
 }
 
 impl TripleClient {
@@ -53,7 +52,7 @@ impl TripleClient {
 
     pub fn with_cluster(self, invoker: ClusterInvoker) -> Self {
         TripleClient {
-            cluster_invoker: Some(invoker),
+            cluster: Some(invoker.join()),
             ..self
         }
     }
@@ -78,16 +77,18 @@ impl TripleClient {
         *req.version_mut() = http::Version::HTTP_2;
         req.headers_mut()
             .insert("method", HeaderValue::from_static("POST"));
-        req.headers_mut().insert(
-            "scheme",
-            HeaderValue::from_str(uri.scheme_str().unwrap()).unwrap(),
-        );
+        if let Some(scheme) = uri.scheme_str() {
+            req.headers_mut()
+                .insert("scheme", HeaderValue::from_str(scheme).unwrap());
+        }
         req.headers_mut()
             .insert("path", HeaderValue::from_str(uri.path()).unwrap());
-        req.headers_mut().insert(
-            "authority",
-            HeaderValue::from_str(uri.authority().unwrap().as_str()).unwrap(),
-        );
+        if let Some(authority) = uri.authority() {
+            req.headers_mut().insert(
+                "authority",
+                HeaderValue::from_str(authority.as_str()).unwrap(),
+            );
+        }
         req.headers_mut().insert(
             "content-type",
             HeaderValue::from_static("application/grpc+proto"),
@@ -130,6 +131,52 @@ impl TripleClient {
         req
     }
 
+    /// Sends the request through the cluster, the provider address is filled in by the cluster.
+    async fn call_cluster(
+        &self,
+        path: http::uri::PathAndQuery,
+        mut invocation: RpcInvocation,
+        body: SdkBody,
+    ) -> Result<http::Response<crate::BoxBody>, crate::status::Status> {
+        let cluster = match self.cluster.as_ref() {
+            Some(v) => v.clone(),
+            None => {
+                return Err(crate::status::Status::new(
+                    crate::status::Code::FailedPrecondition,
+                    "client is built without directory".to_string(),
+                ))
+            }
+        };
+        let mut req = self.map_request(http::Uri::default(), path, body);
+        for key in PROPAGATED_KEYS {
+            // the metadata of the request wins over the one of the request being served
+            let value = match invocation.get_attachment(key) {
+                Some(value) => value.to_string(),
+                None => match RpcContext::get_propagated_attachment(key) {
+                    Some(value) => {
+                        invocation = invocation.with_attachment(key.to_string(), value.clone());
+                        value
+                    }
+                    None => continue,
+                },
+            };
+            if let Ok(value) = HeaderValue::from_str(&value) {
+                req.headers_mut().insert(key, value);
+            }
+        }
+        req.extensions_mut().insert(Arc::new(invocation));
+
+        let resp = cluster
+            .oneshot(req)
+            .await
+            .map_err(crate::status::Status::from_error)?;
+        // a trailers-only response carries the status in its headers, without any message
+        match crate::status::Status::from_header_map(resp.headers()) {
+            Some(status) if status.code() != crate::status::Code::Ok => Err(status),
+            _ => Ok(resp),
+        }
+    }
+
     pub async fn unary<C, M1, M2>(
         &mut self,
         req: Request<M1>,
@@ -142,6 +189,7 @@ impl TripleClient {
         M1: Send + Sync + 'static,
         M2: Send + Sync + 'static,
     {
+        let invocation = invocation.with_metadata(&req.metadata);
         let req = req.map(|m| stream::once(future::ready(m)));
         let body_stream = encode(
             codec.encoder(),
@@ -150,31 +198,11 @@ impl TripleClient {
         )
         .into_stream();
         let body = hyper::Body::wrap_stream(body_stream);
-        let sdk_body = SdkBody::from(body);
-        let arc_invocation = Arc::new(invocation);
-        let req;
-        let http_uri;
-        if self.cluster_invoker.is_some() {
-            let cluster_invoker = self.cluster_invoker.as_ref().unwrap().clone();
-            req = cluster_invoker.build_req(self, path, arc_invocation.clone(), sdk_body);
-            http_uri = req.uri().clone();
-        } else {
-            let url_list = self
-                .directory
-                .as_ref()
-                .expect("msg")
-                .list(arc_invocation.clone());
-            let real_url = url_list.choose(&mut rand::thread_rng()).expect("msg");
-            http_uri =
-                http::Uri::from_str(&format!("http://{}:{}/", real_url.ip, real_url.port)).unwrap();
-            req = self.map_request(http_uri.clone(), path, sdk_body);
-        }
-
-        let mut conn = Connection::new().with_host(http_uri);
-        let response = conn
-            .call(req)
-            .await
-            .map_err(|err| crate::status::Status::from_error(err.into()));
+        let bytes = buffered_body(body).await?;
+        let invocation = invocation.with_arguments(bytes.clone());
+        let response = self
+            .call_cluster(path, invocation, SdkBody::from(bytes))
+            .await;
 
         match response {
             Ok(v) => {
@@ -217,6 +245,7 @@ impl TripleClient {
         M2: Send + Sync + 'static,
     {
         let req = req.into_streaming_request();
+        let invocation = invocation.with_metadata(&req.metadata);
         let en = encode(
             codec.encoder(),
             req.into_inner().map(Ok),
@@ -225,29 +254,7 @@ impl TripleClient {
         .into_stream();
         let body = hyper::Body::wrap_stream(en);
         let sdk_body = SdkBody::from(body);
-        let arc_invocation = Arc::new(invocation);
-        let req;
-        let http_uri;
-        if self.cluster_invoker.is_some() {
-            let cluster_invoker = self.cluster_invoker.as_ref().unwrap().clone();
-            req = cluster_invoker.build_req(self, path, arc_invocation.clone(), sdk_body);
-            http_uri = req.uri().clone();
-        } else {
-            let url_list = self
-                .directory
-                .as_ref()
-                .expect("msg")
-                .list(arc_invocation.clone());
-            let real_url = url_list.choose(&mut rand::thread_rng()).expect("msg");
-            http_uri =
-                http::Uri::from_str(&format!("http://{}:{}/", real_url.ip, real_url.port)).unwrap();
-            req = self.map_request(http_uri.clone(), path, sdk_body);
-        }
-        let mut conn = Connection::new().with_host(http_uri);
-        let response = conn
-            .call(req)
-            .await
-            .map_err(|err| crate::status::Status::from_error(err.into()));
+        let response = self.call_cluster(path, invocation, sdk_body).await;
 
         match response {
             Ok(v) => {
@@ -274,6 +281,7 @@ impl TripleClient {
         M2: Send + Sync + 'static,
     {
         let req = req.into_streaming_request();
+        let invocation = invocation.with_metadata(&req.metadata);
         let en = encode(
             codec.encoder(),
             req.into_inner().map(Ok),
@@ -282,29 +290,7 @@ impl TripleClient {
         .into_stream();
         let body = hyper::Body::wrap_stream(en);
         let sdk_body = SdkBody::from(body);
-        let arc_invocation = Arc::new(invocation);
-        let req;
-        let http_uri;
-        if self.cluster_invoker.is_some() {
-            let cluster_invoker = self.cluster_invoker.as_ref().unwrap().clone();
-            req = cluster_invoker.build_req(self, path, arc_invocation.clone(), sdk_body);
-            http_uri = req.uri().clone();
-        } else {
-            let url_list = self
-                .directory
-                .as_ref()
-                .expect("msg")
-                .list(arc_invocation.clone());
-            let real_url = url_list.choose(&mut rand::thread_rng()).expect("msg");
-            http_uri =
-                http::Uri::from_str(&format!("http://{}:{}/", real_url.ip, real_url.port)).unwrap();
-            req = self.map_request(http_uri.clone(), path, sdk_body);
-        }
-        let mut conn = Connection::new().with_host(http_uri);
-        let response = conn
-            .call(req)
-            .await
-            .map_err(|err| crate::status::Status::from_error(err.into()));
+        let response = self.call_cluster(path, invocation, sdk_body).await;
 
         match response {
             Ok(v) => {
@@ -346,6 +332,7 @@ impl TripleClient {
         M1: Send + Sync + 'static,
         M2: Send + Sync + 'static,
     {
+        let invocation = invocation.with_metadata(&req.metadata);
         let req = req.map(|m| stream::once(future::ready(m)));
         let en = encode(
             codec.encoder(),
@@ -354,31 +341,11 @@ impl TripleClient {
         )
         .into_stream();
         let body = hyper::Body::wrap_stream(en);
-        let sdk_body = SdkBody::from(body);
-        let arc_invocation = Arc::new(invocation);
-        let req;
-        let http_uri;
-        if self.cluster_invoker.is_some() {
-            let cluster_invoker = self.cluster_invoker.as_ref().unwrap().clone();
-            req = cluster_invoker.build_req(self, path, arc_invocation.clone(), sdk_body);
-            http_uri = req.uri().clone();
-        } else {
-            let url_list = self
-                .directory
-                .as_ref()
-                .expect("msg")
-                .list(arc_invocation.clone());
-            let real_url = url_list.choose(&mut rand::thread_rng()).expect("msg");
-            http_uri =
-                http::Uri::from_str(&format!("http://{}:{}/", real_url.ip, real_url.port)).unwrap();
-            req = self.map_request(http_uri.clone(), path, sdk_body);
-        }
-
-        let mut conn = Connection::new().with_host(http_uri);
-        let response = conn
-            .call(req)
-            .await
-            .map_err(|err| crate::status::Status::from_error(err.into()));
+        let bytes = buffered_body(body).await?;
+        let invocation = invocation.with_arguments(bytes.clone());
+        let response = self
+            .call_cluster(path, invocation, SdkBody::from(bytes))
+            .await;
 
         match response {
             Ok(v) => {
@@ -392,3 +359,10 @@ impl TripleClient {
         }
     }
 }
+
+/// Buffers a single message body so that it can be replayed by the cluster.
+async fn buffered_body(body: hyper::Body) -> Result<bytes::Bytes, crate::status::Status> {
+    hyper::body::to_bytes(body)
+        .await
+        .map_err(|err| crate::status::Status::new(crate::status::Code::Internal, err.to_string()))
+}
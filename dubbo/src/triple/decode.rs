@@ -121,7 +121,7 @@ impl<T> Decoding<T> {
                 }
             };
             let len = self.buf.get_u32() as usize;
-            self.buf.reserve(len as usize);
+            self.buf.reserve(len);
 
             self.state = State::ReadBody { len, is_compressed }
         }
@@ -77,7 +77,7 @@ where
 
                     yield Ok(buf.split_to(len + super::consts::HEADER_SIZE).freeze());
                 },
-                Some(Err(err)) => yield Err(err.into()),
+                Some(Err(err)) => yield Err(err),
                 None => break,
             }
         }
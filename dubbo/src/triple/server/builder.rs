@@ -19,17 +19,24 @@ use std::{
     net::{SocketAddr, ToSocketAddrs},
     path::Path,
     str::FromStr,
+    sync::Arc,
 };
 
 use dubbo_base::Url;
 use dubbo_logger::tracing;
+use futures_core::Future;
 use http::{Request, Response, Uri};
 use hyper::body::Body;
+use tokio_rustls::rustls::{Certificate, PrivateKey, ServerConfig};
 use tower_service::Service;
-use tokio_rustls::rustls::{Certificate, PrivateKey};
 
-use crate::{common::url::Url, triple::transport::DubboServer};
-use crate::{utils, BoxBody};
+use crate::{
+    health::{
+        generated::health_server::HealthServer, HealthReporter, HealthService, HEALTH_SERVICE_NAME,
+    },
+    triple::transport::DubboServer,
+};
+use crate::{utils, utils::tls::CertProvider, BoxBody};
 
 #[derive(Clone, Default, Debug)]
 pub struct ServerBuilder {
@@ -37,8 +44,11 @@ pub struct ServerBuilder {
     pub addr: Option<SocketAddr>,
     pub certs: Vec<Certificate>,
     pub keys: Vec<PrivateKey>,
+    pub client_ca: Option<Vec<Certificate>>,
+    cert_provider: Option<Arc<CertProvider<ServerConfig>>>,
     pub service_names: Vec<String>,
     server: DubboServer,
+    health: HealthReporter,
 }
 
 impl ServerBuilder {
@@ -70,6 +80,32 @@ impl ServerBuilder {
         }
     }
 
+    /// Verifies the certs of the TLS clients against the ca certs of the PEM file `client_ca`,
+    /// fails when it has none. A [`CertProvider`] verifies the client certs by its own config.
+    pub fn with_client_ca(self, client_ca: &str) -> Result<ServerBuilder, crate::StdError> {
+        let certs = utils::tls::load_certs(Path::new(client_ca))
+            .map_err(|err| format!("error loading tls client ca {}: {}", client_ca, err))?;
+        if certs.is_empty() {
+            return Err(format!("no cert in tls client ca {}", client_ca).into());
+        }
+        Ok(Self {
+            client_ca: Some(certs),
+            ..self
+        })
+    }
+
+    /// Serves TLS by the config of `provider`, eg. one reloaded when its files change:
+    /// ```ignore
+    /// let provider = CertProvider::server(cert, key, Some(client_ca))?;
+    /// provider.watch(DEFAULT_WATCH_INTERVAL);
+    /// ```
+    pub fn with_cert_provider(self, provider: Arc<CertProvider<ServerConfig>>) -> ServerBuilder {
+        Self {
+            cert_provider: Some(provider),
+            ..self
+        }
+    }
+
     pub fn with_addr(self, addr: &'static str) -> ServerBuilder {
         Self {
             addr: addr.to_socket_addrs().unwrap().next(),
@@ -77,6 +113,21 @@ impl ServerBuilder {
         }
     }
 
+    /// Reports the statuses of the built-in health service by `reporter` instead of the one
+    /// created with the builder.
+    pub fn with_health_reporter(self, reporter: HealthReporter) -> ServerBuilder {
+        Self {
+            health: reporter,
+            ..self
+        }
+    }
+
+    /// The reporter of the built-in health service of this server, set the status of the
+    /// services by it.
+    pub fn health_reporter(&self) -> HealthReporter {
+        self.health.clone()
+    }
+
     pub fn with_service_names(self, service_names: Vec<String>) -> ServerBuilder {
         Self {
             service_names,
@@ -88,11 +139,18 @@ impl ServerBuilder {
         let mut server = self.server.with_listener(self.listener.clone());
 
         {
-            if self.certs.len() != 0 && self.keys.len() != 0 {
+            if !self.certs.is_empty() && !self.keys.is_empty() {
                 server = server.with_tls(self.certs.clone(), self.keys.clone());
             }
+            if let Some(client_ca) = self.client_ca.clone() {
+                server = server.with_client_ca(client_ca);
+            }
+            if let Some(provider) = self.cert_provider.clone() {
+                server = server.with_cert_provider(provider);
+            }
         }
 
+        let mut registered = Vec::new();
         {
             let lock = crate::protocol::triple::TRIPLE_SERVICES.read().unwrap();
             for name in self.service_names.iter() {
@@ -103,9 +161,26 @@ impl ServerBuilder {
                 let svc = lock.get(name).unwrap();
 
                 server = server.add_service(name.clone(), svc.clone());
+                registered.push(name.clone());
             }
         }
 
+        // the built-in health service, unless the user registers one
+        if !registered.iter().any(|name| name == HEALTH_SERVICE_NAME) {
+            let reporter = self.health.clone();
+            reporter.resume();
+            reporter.set_serving("");
+            for name in registered.iter() {
+                reporter.set_serving(name);
+            }
+            server = server
+                .add_service(
+                    HEALTH_SERVICE_NAME.to_string(),
+                    HealthServer::new(HealthService::new(reporter.clone())),
+                )
+                .with_health_reporter(reporter);
+        }
+
         {}
         Self { server, ..self }
     }
@@ -125,10 +200,23 @@ impl ServerBuilder {
         }
     }
 
+    /// Serves until the returned future is dropped, the health service reports NOT_SERVING
+    /// from then on.
     pub async fn serve(self) -> Result<(), crate::Error> {
         tracing::info!("server starting. addr: {:?}", self.addr.unwrap());
         self.server.serve(self.addr.unwrap()).await
     }
+
+    /// Serves until `signal` completes, the health service reports NOT_SERVING from then on.
+    pub async fn serve_with_graceful(
+        self,
+        signal: impl Future<Output = ()>,
+    ) -> Result<(), crate::Error> {
+        tracing::info!("server starting. addr: {:?}", self.addr.unwrap());
+        self.server
+            .serve_with_graceful(self.addr.unwrap(), signal)
+            .await
+    }
 }
 
 impl From<Url> for ServerBuilder {
@@ -150,6 +238,87 @@ impl From<Url> for ServerBuilder {
             server: DubboServer::default(),
             certs: Vec::new(),
             keys: Vec::new(),
+            client_ca: None,
+            cert_provider: None,
+            health: HealthReporter::new(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::health::ServingStatus;
+
+    use super::*;
+
+    fn server(health: HealthReporter) -> ServerBuilder {
+        ServerBuilder::new()
+            .with_listener("tcp".to_string())
+            .with_addr("127.0.0.1:0")
+            .with_health_reporter(health)
+            .build()
+    }
+
+    #[tokio::test]
+    async fn health_per_server() {
+        let (first_health, second_health) = (HealthReporter::new(), HealthReporter::new());
+        let first = server(first_health.clone());
+        let second = server(second_health.clone());
+        assert!(second.health_reporter().get_service_status("").is_some());
+        let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+        let first_serving = tokio::spawn(first.serve_with_graceful(async {
+            let _ = stopped.await;
+        }));
+        let _second_serving = tokio::spawn(second.serve());
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        stop.send(()).unwrap();
+        first_serving.await.unwrap().unwrap();
+        assert_eq!(
+            first_health.get_service_status(""),
+            Some(ServingStatus::NotServing)
+        );
+        assert_eq!(
+            second_health.get_service_status(""),
+            Some(ServingStatus::Serving)
+        );
+
+        // a server built again with the reporter serves again
+        let _restarted = server(first_health.clone());
+        assert_eq!(
+            first_health.get_service_status(""),
+            Some(ServingStatus::Serving)
+        );
+    }
+
+    #[tokio::test]
+    async fn client_ca_errors() {
+        let testdata = Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/tls");
+        let path = |name: &str| testdata.join(name).to_str().unwrap().to_string();
+        assert!(ServerBuilder::new()
+            .with_client_ca(&path("none.pem"))
+            .is_err());
+        // a key file has no cert
+        assert!(ServerBuilder::new()
+            .with_client_ca(&path("server.key"))
+            .is_err());
+
+        // the cert provider has its own client ca
+        let provider = CertProvider::server(
+            testdata.join("server.pem"),
+            testdata.join("server.key"),
+            None,
+        )
+        .unwrap();
+        let builder = ServerBuilder::new()
+            .with_listener("tcp".to_string())
+            .with_addr("127.0.0.1:0")
+            .with_cert_provider(provider)
+            .with_client_ca(&path("ca.pem"))
+            .unwrap()
+            .build();
+        assert!(builder.serve().await.is_err());
+    }
+}
@@ -19,6 +19,7 @@ use futures_util::{future, stream, StreamExt, TryStreamExt};
 use http_body::Body;
 
 use crate::{
+    context::RpcContext,
     invocation::Request,
     triple::{
         codec::Codec,
@@ -73,9 +74,10 @@ where
             Err(status) => return status.to_http(),
         };
 
+        let headers = req.headers().clone();
         let req_stream = req.map(|body| Decoding::new(body, self.codec.decoder(), compression));
 
-        let resp = service.call(Request::from_http(req_stream)).await;
+        let resp = RpcContext::scope(&headers, service.call(Request::from_http(req_stream))).await;
 
         let (mut parts, resp_body) = match resp {
             Ok(v) => v.into_http().into_parts(),
@@ -125,9 +127,10 @@ where
             Err(status) => return status.to_http(),
         };
 
+        let headers = req.headers().clone();
         let req_stream = req.map(|body| Decoding::new(body, self.codec.decoder(), compression));
 
-        let resp = service.call(Request::from_http(req_stream)).await;
+        let resp = RpcContext::scope(&headers, service.call(Request::from_http(req_stream))).await;
 
         let (mut parts, resp_body) = match resp {
             Ok(v) => v.into_http().into_parts(),
@@ -182,7 +185,8 @@ where
             Err(err) => return err.to_http(),
         };
 
-        let resp = service.call(Request::from_parts(parts, msg)).await;
+        let headers = parts.into_headers();
+        let resp = RpcContext::scope(&headers, service.call(Request::from_parts(parts, msg))).await;
 
         let (mut parts, resp_body) = match resp {
             Ok(v) => v.into_http().into_parts(),
@@ -233,7 +237,8 @@ where
             Err(err) => return err.to_http(),
         };
 
-        let resp = service.call(Request::from_parts(parts, msg)).await;
+        let headers = parts.into_headers();
+        let resp = RpcContext::scope(&headers, service.call(Request::from_parts(parts, msg))).await;
 
         let (mut parts, resp_body) = match resp {
             Ok(v) => v.into_http().into_parts(),
@@ -17,17 +17,18 @@
 
 use std::task::Poll;
 
-use dubbo_logger::tracing::debug;
-use hyper::client::{conn::Builder, service::Connect};
+use bytes::Buf;
+use http_body::Body;
 use tower_service::Service;
 
-use crate::{boxed, triple::transport::connector::get_connector};
+use crate::{boxed, triple::transport::pool::ConnectionPool};
 
+/// A connection to a host, the calls share the pooled HTTP/2 connection to the host.
 #[derive(Debug, Clone)]
 pub struct Connection {
     host: hyper::Uri,
     connector: &'static str,
-    builder: Builder,
+    pool: Option<ConnectionPool>,
 }
 
 impl Default for Connection {
@@ -41,7 +42,7 @@ impl Connection {
         Connection {
             host: hyper::Uri::default(),
             connector: "http",
-            builder: Builder::new(),
+            pool: None,
         }
     }
 
@@ -55,8 +56,9 @@ impl Connection {
         self
     }
 
-    pub fn with_builder(mut self, builder: Builder) -> Self {
-        self.builder = builder;
+    /// Uses `pool` instead of the pool shared by the connections of the connector.
+    pub fn with_pool(mut self, pool: ConnectionPool) -> Self {
+        self.pool = Some(pool);
         self
     }
 }
@@ -81,19 +83,23 @@ where
     }
 
     fn call(&mut self, req: http::Request<ReqBody>) -> Self::Future {
-        let builder = self.builder.clone().http2_only(true).to_owned();
-        let mut connector = Connect::new(get_connector(self.connector), builder);
-        let uri = self.host.clone();
-        let fut = async move {
-            debug!("send base call to {}", uri);
-            let mut con = connector.call(uri).await.unwrap();
-
-            con.call(req)
-                .await
-                .map_err(|err| err.into())
-                .map(|res| res.map(boxed))
+        let pool = match self.pool.as_ref() {
+            Some(v) => v.clone(),
+            None => ConnectionPool::shared(self.connector),
         };
-
-        Box::pin(fut)
+        let (mut parts, body) = req.into_parts();
+        // the path of the request to the host of the connection
+        let mut uri = self.host.clone().into_parts();
+        uri.path_and_query = parts.uri.path_and_query().cloned();
+        parts.uri = match http::Uri::from_parts(uri) {
+            Ok(v) => v,
+            Err(err) => return Box::pin(async move { Err(err.into()) }),
+        };
+        let body = body
+            .map_data(|mut data| data.copy_to_bytes(data.remaining()))
+            .map_err(Into::into)
+            .boxed_unsync();
+        let req = http::Request::from_parts(parts, body);
+        Box::pin(async move { pool.send(req).await.map(|res| res.map(boxed)) })
     }
 }
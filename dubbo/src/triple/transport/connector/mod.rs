@@ -16,7 +16,8 @@
  */
 
 pub mod http_connector;
-#[cfg(any(target_os = "macos", target_os = "unix"))]
+pub mod tls_connector;
+#[cfg(unix)]
 pub mod unix_connector;
 
 use hyper::Uri;
@@ -79,7 +80,7 @@ pub fn get_connector(connector: &'static str) -> BoxCloneService<Uri, BoxIO, cra
             let c = http_connector::HttpConnector::new();
             BoxCloneService::new(Connector::new(c))
         }
-        #[cfg(any(target_os = "macos", target_os = "unix"))]
+        #[cfg(unix)]
         "unix" => {
             let c = unix_connector::UnixConnector::new();
             BoxCloneService::new(Connector::new(c))
@@ -0,0 +1,350 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use dubbo_config::tls::TlsConfig;
+use http::Uri;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_rustls::{
+    client::TlsStream,
+    rustls::{Certificate, ClientConfig, PrivateKey, ServerName},
+};
+use tower_service::Service;
+
+use super::http_connector::HttpConnector;
+use crate::{
+    utils,
+    utils::tls::{CertProvider, DEFAULT_WATCH_INTERVAL},
+};
+
+/// TLS of the client. The server cert is verified against `ca_certs` and the provider host, or
+/// the server name when set. The client presents its identity, if any, for mutual TLS.
+#[derive(Clone, Default)]
+pub struct ClientTlsConfig {
+    ca_certs: Vec<Certificate>,
+    identity: Option<(Vec<Certificate>, PrivateKey)>,
+    server_name: Option<String>,
+}
+
+impl fmt::Debug for ClientTlsConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClientTlsConfig")
+            .field("ca_certs", &self.ca_certs.len())
+            .field("identity", &self.identity.is_some())
+            .field("server_name", &self.server_name)
+            .finish()
+    }
+}
+
+impl ClientTlsConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads the files named by `config`.
+    pub fn from_config(config: &TlsConfig) -> io::Result<Self> {
+        let mut tls = ClientTlsConfig::new();
+        if !config.ca.is_empty() {
+            tls = tls.with_ca_certs(utils::tls::load_certs(Path::new(&config.ca))?);
+        }
+        match (config.cert.is_empty(), config.key.is_empty()) {
+            (true, true) => {}
+            (false, false) => {
+                let certs = utils::tls::load_certs(Path::new(&config.cert))?;
+                let key = utils::tls::load_keys(Path::new(&config.key))?
+                    .into_iter()
+                    .next()
+                    .ok_or_else(|| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            format!("no key in {}", config.key),
+                        )
+                    })?;
+                tls = tls.with_identity(certs, key);
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "tls cert and key must be given together",
+                ))
+            }
+        }
+        if !config.server_name.is_empty() {
+            tls = tls.with_server_name(config.server_name.clone());
+        }
+        Ok(tls)
+    }
+
+    pub fn with_ca_certs(self, ca_certs: Vec<Certificate>) -> Self {
+        Self { ca_certs, ..self }
+    }
+
+    /// The cert chain and key presented to the server for mutual TLS.
+    pub fn with_identity(self, certs: Vec<Certificate>, key: PrivateKey) -> Self {
+        Self {
+            identity: Some((certs, key)),
+            ..self
+        }
+    }
+
+    pub fn with_server_name(self, server_name: String) -> Self {
+        Self {
+            server_name: Some(server_name),
+            ..self
+        }
+    }
+
+    /// The rustls config, which negotiates `h2` by ALPN.
+    pub fn client_config(&self) -> Result<ClientConfig, crate::Error> {
+        let builder = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(utils::tls::root_store(&self.ca_certs)?);
+        let mut config = match self.identity.clone() {
+            Some((certs, key)) => builder.with_single_cert(certs, key)?,
+            None => builder.with_no_client_auth(),
+        };
+        config.alpn_protocols = vec![b"h2".to_vec()];
+        Ok(config)
+    }
+
+    pub fn connector(&self) -> Result<TlsConnector, crate::Error> {
+        let connector = TlsConnector::new(Arc::new(self.client_config()?));
+        Ok(match self.server_name.clone() {
+            Some(name) => connector.with_server_name(name),
+            None => connector,
+        })
+    }
+}
+
+/// Connects by TLS over the connection of the inner connector, the handshakes use the latest
+/// config of the cert provider.
+#[derive(Clone)]
+pub struct TlsConnector<C = HttpConnector> {
+    inner: C,
+    provider: Arc<CertProvider<ClientConfig>>,
+    server_name: Option<String>,
+}
+
+impl<C> fmt::Debug for TlsConnector<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TlsConnector")
+            .field("server_name", &self.server_name)
+            .finish()
+    }
+}
+
+impl TlsConnector {
+    pub fn new(config: Arc<ClientConfig>) -> Self {
+        Self::with_provider(CertProvider::fixed(config))
+    }
+
+    pub fn with_provider(provider: Arc<CertProvider<ClientConfig>>) -> Self {
+        Self::new_with_connector(HttpConnector::new(), provider)
+    }
+
+    /// Connects by the files named by `config`, they are reloaded when they change if the
+    /// config says to watch them.
+    pub fn from_config(config: &TlsConfig) -> Result<Self, crate::Error> {
+        let files = [&config.ca, &config.cert, &config.key]
+            .into_iter()
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from)
+            .collect();
+        let tls = config.clone();
+        let provider = CertProvider::new(files, move || {
+            ClientTlsConfig::from_config(&tls)?.client_config()
+        })?;
+        if config.watch {
+            provider.watch(DEFAULT_WATCH_INTERVAL);
+        }
+        let connector = TlsConnector::with_provider(provider);
+        Ok(match config.server_name.as_str() {
+            "" => connector,
+            name => connector.with_server_name(name.to_string()),
+        })
+    }
+}
+
+impl<C> TlsConnector<C> {
+    pub fn new_with_connector(
+        inner: C,
+        provider: Arc<CertProvider<ClientConfig>>,
+    ) -> TlsConnector<C> {
+        Self {
+            inner,
+            provider,
+            server_name: None,
+        }
+    }
+
+    /// The provider of the config, its `reload` applies to the connections made afterwards.
+    pub fn provider(&self) -> &Arc<CertProvider<ClientConfig>> {
+        &self.provider
+    }
+
+    /// Verifies the server cert against `server_name` instead of the host of the uri.
+    pub fn with_server_name(self, server_name: String) -> Self {
+        Self {
+            server_name: Some(server_name),
+            ..self
+        }
+    }
+}
+
+impl<C> Service<Uri> for TlsConnector<C>
+where
+    C: Service<Uri> + Send + 'static,
+    C::Response: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    C::Future: Send + 'static,
+    C::Error: Into<crate::Error>,
+{
+    type Response = TlsStream<C::Response>;
+
+    type Error = crate::Error;
+
+    type Future = crate::BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let name = self
+            .server_name
+            .clone()
+            .or_else(|| uri.host().map(String::from))
+            .unwrap_or_default();
+        let tls = tokio_rustls::TlsConnector::from(self.provider.config());
+        let connect = self.inner.call(uri);
+        Box::pin(async move {
+            let server_name = ServerName::try_from(name.as_str()).map_err(|_| {
+                format!(
+                    "invalid tls server name {:?}, set the server name of the tls config",
+                    name
+                )
+            })?;
+            let io = connect.await.map_err(Into::into)?;
+            Ok(tls.connect(server_name, io).await?)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio_rustls::{rustls::ServerConfig, TlsAcceptor};
+
+    use super::*;
+
+    fn testdata(name: &str) -> String {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("testdata/tls")
+            .join(name)
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    /// Accepts one TLS connection of localhost, requiring a client cert when `client_ca` is
+    /// set, and returns the number of certs presented by the client.
+    async fn accept_one(client_ca: bool) -> (Uri, tokio::task::JoinHandle<io::Result<usize>>) {
+        let ca = utils::tls::load_certs(Path::new(&testdata("ca.pem"))).unwrap();
+        let config: ServerConfig = utils::tls::server_config(
+            utils::tls::load_certs(Path::new(&testdata("server.pem"))).unwrap(),
+            utils::tls::load_key(Path::new(&testdata("server.key"))).unwrap(),
+            client_ca.then_some(&ca[..]),
+        )
+        .unwrap();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let uri = format!("http://{}", listener.local_addr().unwrap())
+            .parse()
+            .unwrap();
+        let accepted = tokio::spawn(async move {
+            let (io, _) = listener.accept().await?;
+            let stream = TlsAcceptor::from(Arc::new(config)).accept(io).await?;
+            let peer = stream.get_ref().1.peer_certificates();
+            Ok(peer.map_or(0, |certs| certs.len()))
+        });
+        (uri, accepted)
+    }
+
+    fn tls_config() -> TlsConfig {
+        TlsConfig::default().ca(testdata("ca.pem"))
+    }
+
+    #[test]
+    fn cert_without_key() {
+        let config = TlsConfig::default().cert("client.pem".to_string());
+        let err = ClientTlsConfig::from_config(&config).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn ca_required() {
+        assert!(ClientTlsConfig::new().connector().is_err());
+    }
+
+    #[tokio::test]
+    async fn handshake_by_server_name() {
+        let config = tls_config().server_name("localhost".to_string());
+        let (uri, accepted) = accept_one(false).await;
+        let stream = TlsConnector::from_config(&config)
+            .unwrap()
+            .call(uri)
+            .await
+            .unwrap();
+        assert_eq!(stream.get_ref().1.alpn_protocol(), Some(&b"h2"[..]));
+        assert_eq!(accepted.await.unwrap().unwrap(), 0);
+
+        // the cert is not valid for the address of the uri
+        let (uri, _accepted) = accept_one(false).await;
+        let mut connector = TlsConnector::from_config(&tls_config()).unwrap();
+        assert!(connector.call(uri).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn untrusted_ca() {
+        let config = TlsConfig::default()
+            .ca(testdata("ed25519.pem"))
+            .server_name("localhost".to_string());
+        let (uri, _accepted) = accept_one(false).await;
+        let mut connector = TlsConnector::from_config(&config).unwrap();
+        assert!(connector.call(uri).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn present_client_cert() {
+        let config = tls_config()
+            .server_name("localhost".to_string())
+            .cert(testdata("client.pem"))
+            .key(testdata("client.key"));
+        let (uri, accepted) = accept_one(true).await;
+        let _stream = TlsConnector::from_config(&config)
+            .unwrap()
+            .call(uri)
+            .await
+            .unwrap();
+        assert_eq!(accepted.await.unwrap().unwrap(), 1);
+    }
+}
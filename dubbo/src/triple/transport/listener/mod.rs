@@ -16,7 +16,7 @@
  */
 
 pub mod tcp_listener;
-#[cfg(any(target_os = "macos", target_os = "unix"))]
+#[cfg(unix)]
 pub mod unix_listener;
 
 use std::net::SocketAddr;
@@ -65,7 +65,7 @@ impl<T: Listener> Listener for WrappedListener<T> {
 pub async fn get_listener(name: String, addr: SocketAddr) -> Result<BoxListener, crate::Error> {
     match name.as_str() {
         "tcp" => Ok(TcpListener::bind(addr).await?.boxed()),
-        #[cfg(any(target_os = "macos", target_os = "unix"))]
+        #[cfg(unix)]
         "unix" => Ok(unix_listener::UnixListener::bind(addr).await?.boxed()),
         _ => {
             tracing::warn!("no support listener: {:?}", name);
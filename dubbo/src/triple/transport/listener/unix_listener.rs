@@ -31,7 +31,7 @@ pub struct UnixListener {
 
 impl UnixListener {
     pub async fn bind(addr: SocketAddr) -> std::io::Result<UnixListener> {
-        let listener = tokioUnixListener::bind(format!("{}", addr.to_string()))?;
+        let listener = tokioUnixListener::bind(addr.to_string())?;
 
         Ok(UnixListener {
             inner: listener,
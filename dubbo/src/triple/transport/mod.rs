@@ -19,6 +19,7 @@ pub mod connection;
 pub mod connector;
 mod io;
 pub mod listener;
+pub mod pool;
 pub mod resolver;
 pub mod router;
 pub mod service;
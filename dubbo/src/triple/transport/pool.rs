@@ -0,0 +1,510 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::{
+    collections::HashMap,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use bytes::Bytes;
+use dubbo_logger::tracing;
+use http_body::{combinators::UnsyncBoxBody, Body};
+use hyper::{client::Client, Uri};
+use lazy_static::lazy_static;
+use pin_project::pin_project;
+use tower_service::Service;
+
+use crate::{
+    status::{Code, Status},
+    triple::transport::{
+        connector::{get_connector, tls_connector::TlsConnector, Connector},
+        io::BoxIO,
+    },
+    utils::boxed_clone::BoxCloneService,
+};
+
+/// Idle connections are closed after this timeout.
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+/// Open streams of a connection above which another connection is used.
+pub const DEFAULT_MAX_STREAMS: usize = 100;
+/// The first reconnect to a failed endpoint waits this long, doubling on each failure.
+pub const INITIAL_RECONNECT_BACKOFF: Duration = Duration::from_millis(500);
+pub const MAX_RECONNECT_BACKOFF: Duration = Duration::from_secs(30);
+/// Pings of the connections, a connection is closed when a ping is not answered in time.
+pub const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(30);
+pub const KEEP_ALIVE_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub(crate) type PoolBody = UnsyncBoxBody<Bytes, crate::Error>;
+
+lazy_static! {
+    static ref SHARED_POOLS: Mutex<HashMap<&'static str, ConnectionPool>> =
+        Mutex::new(HashMap::new());
+}
+
+/// Pool of HTTP/2 connections, which multiplex the calls to an endpoint.
+///
+/// The pool keeps `connections` connections to each endpoint, used in turn. A connection with
+/// `max_streams` open streams is skipped, and another connection to the endpoint is opened
+/// once all of them are full. These extra connections are closed again when their streams are
+/// done, so the pool gets back to `connections` connections after a burst of calls.
+///
+/// Broken connections are evicted and connected again on the next call, idle ones are closed
+/// after the idle timeout. The connections of an endpoint without calls for the idle timeout,
+/// eg. a provider gone offline, are dropped from the pool. After a failed connect, the calls to
+/// the endpoint fail fast with `Unavailable` until the reconnect backoff elapses.
+#[derive(Clone)]
+pub struct ConnectionPool {
+    connector: &'static str,
+    config: PoolConfig,
+    inner: Arc<PoolInner>,
+}
+
+#[derive(Debug, Clone)]
+struct PoolConfig {
+    idle_timeout: Duration,
+    connections: usize,
+    max_streams: usize,
+    tls: Option<TlsConnector>,
+}
+
+struct PoolInner {
+    connector: PoolConnector,
+    config: PoolConfig,
+    // keyed by authority
+    endpoints: Mutex<HashMap<String, Endpoint>>,
+}
+
+/// The connections to an endpoint.
+struct Endpoint {
+    // each client keeps one connection to the endpoint
+    clients: Vec<Client<PoolConnector, PoolBody>>,
+    // open streams of each client
+    streams: Vec<usize>,
+    next: usize,
+    last_used: Instant,
+}
+
+impl std::fmt::Debug for ConnectionPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConnectionPool")
+            .field("connector", &self.connector)
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+impl ConnectionPool {
+    pub fn new(connector: &'static str) -> Self {
+        let config = PoolConfig {
+            idle_timeout: DEFAULT_IDLE_TIMEOUT,
+            connections: 1,
+            max_streams: DEFAULT_MAX_STREAMS,
+            tls: None,
+        };
+        Self::with_config(connector, config)
+    }
+
+    fn with_config(connector: &'static str, config: PoolConfig) -> Self {
+        let pool_connector = PoolConnector {
+            name: connector,
+            tls: config.tls.clone(),
+            backoff: Arc::new(Mutex::new(HashMap::new())),
+        };
+        let inner = PoolInner {
+            connector: pool_connector,
+            config: config.clone(),
+            endpoints: Mutex::new(HashMap::new()),
+        };
+        ConnectionPool {
+            connector,
+            config,
+            inner: Arc::new(inner),
+        }
+    }
+
+    pub fn with_idle_timeout(self, idle_timeout: Duration) -> Self {
+        let config = PoolConfig {
+            idle_timeout,
+            ..self.config
+        };
+        Self::with_config(self.connector, config)
+    }
+
+    /// Keeps `connections` connections to each endpoint, at least one.
+    pub fn with_connections(self, connections: usize) -> Self {
+        let config = PoolConfig {
+            connections: connections.max(1),
+            ..self.config
+        };
+        Self::with_config(self.connector, config)
+    }
+
+    pub fn with_max_streams(self, max_streams: usize) -> Self {
+        let config = PoolConfig {
+            max_streams: max_streams.max(1),
+            ..self.config
+        };
+        Self::with_config(self.connector, config)
+    }
+
+    /// Connects to the endpoints by `tls` instead of the connector.
+    pub fn with_tls(self, tls: TlsConnector) -> Self {
+        let config = PoolConfig {
+            tls: Some(tls),
+            ..self.config
+        };
+        Self::with_config(self.connector, config)
+    }
+
+    /// The pool shared by the connections of `connector`.
+    pub fn shared(connector: &'static str) -> Self {
+        SHARED_POOLS
+            .lock()
+            .unwrap()
+            .entry(connector)
+            .or_insert_with(|| ConnectionPool::new(connector))
+            .clone()
+    }
+
+    /// Sends `req` over a connection to the endpoint of its uri.
+    pub(crate) async fn send(
+        &self,
+        req: http::Request<PoolBody>,
+    ) -> Result<http::Response<StreamBody>, crate::Error> {
+        let authority = req
+            .uri()
+            .authority()
+            .map(|v| v.to_string())
+            .unwrap_or_default();
+        let (client, stream) = self.inner.acquire(authority);
+        let res = client.request(req).await.map_err(|err| {
+            tracing::debug!("pooled call failed: {}", err);
+            crate::Error::from(err)
+        })?;
+        Ok(res.map(|inner| StreamBody {
+            inner,
+            _stream: stream,
+        }))
+    }
+}
+
+impl PoolInner {
+    fn new_client(&self) -> Client<PoolConnector, PoolBody> {
+        Client::builder()
+            .http2_only(true)
+            .pool_idle_timeout(self.config.idle_timeout)
+            .http2_keep_alive_interval(KEEP_ALIVE_INTERVAL)
+            .http2_keep_alive_timeout(KEEP_ALIVE_TIMEOUT)
+            .build(self.connector.clone())
+    }
+
+    /// Picks the next connection to `authority` which has room for another stream.
+    fn acquire(
+        self: &Arc<Self>,
+        authority: String,
+    ) -> (Client<PoolConnector, PoolBody>, OpenStream) {
+        let mut endpoints = self.endpoints.lock().unwrap();
+        self.evict_idle(&mut endpoints);
+        let endpoint = endpoints
+            .entry(authority.clone())
+            .or_insert_with(|| Endpoint {
+                clients: (0..self.config.connections)
+                    .map(|_| self.new_client())
+                    .collect(),
+                streams: vec![0; self.config.connections],
+                next: 0,
+                last_used: Instant::now(),
+            });
+        let count = endpoint.clients.len();
+        let start = endpoint.next;
+        endpoint.next = endpoint.next.wrapping_add(1);
+        let index = match (0..count)
+            .map(|i| (start + i) % count)
+            .find(|index| endpoint.streams[*index] < self.config.max_streams)
+        {
+            Some(v) => v,
+            None => {
+                tracing::debug!("connections to {} are full, open another one", authority);
+                endpoint.clients.push(self.new_client());
+                endpoint.streams.push(0);
+                count
+            }
+        };
+        endpoint.streams[index] += 1;
+        endpoint.last_used = Instant::now();
+        let client = endpoint.clients[index].clone();
+        let stream = OpenStream {
+            pool: self.clone(),
+            authority,
+            index,
+        };
+        (client, stream)
+    }
+
+    /// Drops the endpoints without open streams for the idle timeout, with their connections.
+    fn evict_idle(&self, endpoints: &mut HashMap<String, Endpoint>) {
+        let before = endpoints.len();
+        endpoints.retain(|_, endpoint| {
+            endpoint.streams.iter().any(|count| *count > 0)
+                || endpoint.last_used.elapsed() < self.config.idle_timeout
+        });
+        if endpoints.len() < before {
+            tracing::debug!("dropped {} idle endpoints", before - endpoints.len());
+            self.connector
+                .backoff
+                .lock()
+                .unwrap()
+                .retain(|authority, _| endpoints.contains_key(authority));
+        }
+    }
+}
+
+/// An open stream of a pooled connection, closed on drop.
+struct OpenStream {
+    pool: Arc<PoolInner>,
+    authority: String,
+    index: usize,
+}
+
+impl Drop for OpenStream {
+    fn drop(&mut self) {
+        let mut endpoints = self.pool.endpoints.lock().unwrap();
+        if let Some(endpoint) = endpoints.get_mut(&self.authority) {
+            if let Some(count) = endpoint.streams.get_mut(self.index) {
+                *count = count.saturating_sub(1);
+            }
+            endpoint.last_used = Instant::now();
+            // the open streams keep their index, only the last connections can be closed
+            while endpoint.clients.len() > self.pool.config.connections
+                && endpoint.streams.last() == Some(&0)
+            {
+                endpoint.clients.pop();
+                endpoint.streams.pop();
+            }
+        }
+    }
+}
+
+/// Response body of a pooled call, the stream stays open until the body is dropped.
+#[pin_project]
+pub(crate) struct StreamBody {
+    #[pin]
+    inner: hyper::Body,
+    _stream: OpenStream,
+}
+
+impl Body for StreamBody {
+    type Data = Bytes;
+
+    type Error = hyper::Error;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        self.project().inner.poll_data(cx)
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<http::HeaderMap>, Self::Error>> {
+        self.project().inner.poll_trailers(cx)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+/// Connects by the named connector or TLS, failing fast while an endpoint is backing off.
+#[derive(Clone)]
+pub(crate) struct PoolConnector {
+    name: &'static str,
+    tls: Option<TlsConnector>,
+    // failures and the time to connect again, keyed by authority
+    backoff: Arc<Mutex<HashMap<String, (u32, Instant)>>>,
+}
+
+impl PoolConnector {
+    fn record(&self, authority: &str, connected: bool) {
+        let mut backoff = self.backoff.lock().unwrap();
+        if connected {
+            backoff.remove(authority);
+            return;
+        }
+        let failures = backoff.get(authority).map_or(0, |(failures, _)| *failures) + 1;
+        let delay = INITIAL_RECONNECT_BACKOFF
+            .saturating_mul(1u32 << (failures - 1).min(16))
+            .min(MAX_RECONNECT_BACKOFF);
+        tracing::warn!(
+            "connect to {} failed {} times, reconnect in {:?}",
+            authority,
+            failures,
+            delay
+        );
+        backoff.insert(authority.to_string(), (failures, Instant::now() + delay));
+    }
+}
+
+impl Service<Uri> for PoolConnector {
+    type Response = BoxIO;
+
+    type Error = crate::Error;
+
+    type Future = crate::BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let authority = uri.authority().map(|v| v.to_string()).unwrap_or_default();
+        let retry_at = self
+            .backoff
+            .lock()
+            .unwrap()
+            .get(&authority)
+            .map(|(_, retry_at)| *retry_at);
+        if let Some(retry_at) = retry_at.filter(|retry_at| *retry_at > Instant::now()) {
+            let status = Status::new(
+                Code::Unavailable,
+                format!(
+                    "reconnect to {} in {:?}",
+                    authority,
+                    retry_at - Instant::now()
+                ),
+            );
+            return Box::pin(async move { Err(status.into()) });
+        }
+        let this = self.clone();
+        let mut connector = match self.tls.clone() {
+            Some(tls) => BoxCloneService::new(Connector::new(tls)),
+            None => get_connector(self.name),
+        };
+        Box::pin(async move {
+            let result = connector.call(uri).await;
+            this.record(&authority, result.is_ok());
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn backoff_after_failed_connect() {
+        let mut connector = PoolConnector {
+            name: "http",
+            tls: None,
+            backoff: Arc::new(Mutex::new(HashMap::new())),
+        };
+        let uri = "http://127.0.0.1:1".parse::<Uri>().unwrap();
+        assert!(connector.call(uri.clone()).await.is_err());
+        // fails fast without connecting
+        let err = connector.call(uri).await.err().unwrap();
+        assert_eq!(Status::from_error(err).code(), Code::Unavailable);
+    }
+
+    #[test]
+    fn open_connection_when_streams_full() {
+        let pool = ConnectionPool::new("http")
+            .with_connections(2)
+            .with_max_streams(1);
+        let authority = "127.0.0.1:8888".to_string();
+        let (_, first) = pool.inner.acquire(authority.clone());
+        let (_, second) = pool.inner.acquire(authority.clone());
+        assert_ne!(first.index, second.index);
+        let (_, third) = pool.inner.acquire(authority.clone());
+        assert_eq!(third.index, 2);
+        assert_eq!(
+            pool.inner.endpoints.lock().unwrap()[&authority]
+                .clients
+                .len(),
+            3
+        );
+        // the closed stream makes room on its connection
+        let index = first.index;
+        drop(first);
+        let (_, fourth) = pool.inner.acquire(authority);
+        assert_eq!(fourth.index, index);
+    }
+
+    #[test]
+    fn close_extra_connections_after_burst() {
+        let pool = ConnectionPool::new("http")
+            .with_connections(2)
+            .with_max_streams(1);
+        let authority = "127.0.0.1:8888".to_string();
+        let clients = || {
+            pool.inner.endpoints.lock().unwrap()[&authority]
+                .clients
+                .len()
+        };
+        let mut streams: Vec<_> = (0..5)
+            .map(|_| pool.inner.acquire(authority.clone()).1)
+            .collect();
+        assert_eq!(clients(), 5);
+        // the last connection is still busy
+        let last = streams.pop().unwrap();
+        streams.clear();
+        assert_eq!(clients(), 5);
+        drop(last);
+        assert_eq!(clients(), 2);
+        // the connections of the endpoint are reused
+        let (_, stream) = pool.inner.acquire(authority.clone());
+        assert!(stream.index < 2);
+    }
+
+    #[test]
+    fn connections_per_endpoint() {
+        let pool = ConnectionPool::new("http").with_max_streams(1);
+        let (_, first) = pool.inner.acquire("127.0.0.1:8888".to_string());
+        // the full connection of another endpoint is not shared
+        let (_, second) = pool.inner.acquire("127.0.0.1:8889".to_string());
+        assert_eq!((first.index, second.index), (0, 0));
+        let endpoints = pool.inner.endpoints.lock().unwrap();
+        assert_eq!(endpoints["127.0.0.1:8888"].clients.len(), 1);
+        assert_eq!(endpoints["127.0.0.1:8889"].clients.len(), 1);
+    }
+
+    #[test]
+    fn evict_idle_endpoints() {
+        let pool = ConnectionPool::new("http").with_idle_timeout(Duration::from_millis(20));
+        let (_, busy) = pool.inner.acquire("127.0.0.1:8888".to_string());
+        let (_, idle) = pool.inner.acquire("127.0.0.1:8889".to_string());
+        drop(idle);
+        std::thread::sleep(Duration::from_millis(30));
+
+        let _stream = pool.inner.acquire("127.0.0.1:8890".to_string());
+        let endpoints = pool.inner.endpoints.lock().unwrap();
+        assert!(endpoints.contains_key("127.0.0.1:8888"));
+        assert!(!endpoints.contains_key("127.0.0.1:8889"));
+        assert!(endpoints.contains_key("127.0.0.1:8890"));
+        drop(endpoints);
+        drop(busy);
+    }
+}
@@ -15,7 +15,6 @@
  * limitations under the License.
  */
 
-use std::io;
 use std::net::SocketAddr;
 use std::sync::Arc;
 
@@ -24,14 +23,17 @@ use futures_core::Future;
 use http::{Request, Response};
 use hyper::body::Body;
 use tokio::time::Duration;
-use tower_service::Service;
 use tokio_rustls::rustls::{Certificate, PrivateKey};
 use tokio_rustls::{rustls, TlsAcceptor};
+use tower_service::Service;
 
 use super::listener::get_listener;
 use super::router::DubboRouter;
+use crate::context::{PeerIdentity, RpcContext};
+use crate::health::HealthReporter;
 use crate::triple::transport::io::BoxIO;
-use crate::BoxBody;
+use crate::utils::tls::CertProvider;
+use crate::{utils, BoxBody};
 
 #[derive(Default, Clone, Debug)]
 pub struct DubboServer {
@@ -46,6 +48,9 @@ pub struct DubboServer {
     listener: Option<String>,
     certs: Vec<Certificate>,
     keys: Vec<PrivateKey>,
+    client_ca: Option<Vec<Certificate>>,
+    cert_provider: Option<Arc<CertProvider<rustls::ServerConfig>>>,
+    health: Option<HealthReporter>,
 }
 
 impl DubboServer {
@@ -104,8 +109,37 @@ impl DubboServer {
 
     pub fn with_tls(self, certs: Vec<Certificate>, keys: Vec<PrivateKey>) -> Self {
         Self {
-            certs: certs,
-            keys: keys,
+            certs,
+            keys,
+            ..self
+        }
+    }
+
+    /// Requires the TLS clients to present a cert verified against `client_ca`, the handlers
+    /// get the client certs by [`RpcContext::get_peer_identity`].
+    pub fn with_client_ca(self, client_ca: Vec<Certificate>) -> Self {
+        Self {
+            client_ca: Some(client_ca),
+            ..self
+        }
+    }
+
+    /// Serves TLS by the config of `provider` instead of the certs and keys, so that reloaded
+    /// certs apply without restarting the server. The client certs are verified by the config
+    /// of `provider`, the server fails to start when a client ca is set as well.
+    pub fn with_cert_provider(self, provider: Arc<CertProvider<rustls::ServerConfig>>) -> Self {
+        Self {
+            cert_provider: Some(provider),
+            ..self
+        }
+    }
+
+    /// The reporter is shut down when the server stops, either by the graceful signal, by an
+    /// error, or by dropping the serving future, so that the health service reports
+    /// NOT_SERVING.
+    pub fn with_health_reporter(self, reporter: HealthReporter) -> Self {
+        Self {
+            health: Some(reporter),
             ..self
         }
     }
@@ -125,6 +159,9 @@ impl DubboServer {
             listener: None,
             certs: Vec::new(),
             keys: Vec::new(),
+            client_ca: None,
+            cert_provider: None,
+            health: None,
         }
     }
 }
@@ -153,6 +190,7 @@ impl DubboServer {
         addr: SocketAddr,
         signal: impl Future<Output = ()>,
     ) -> Result<(), crate::Error> {
+        let _health = HealthGuard(self.health.clone());
         let svc = self.router.clone();
         tokio::pin!(signal);
 
@@ -169,19 +207,26 @@ impl DubboServer {
             }
         };
 
-        let acceptor: Option<TlsAcceptor>;
-        if self.certs.len() != 0 && !self.keys.len() != 0 {
+        let tls: Option<Arc<CertProvider<rustls::ServerConfig>>>;
+        if let Some(provider) = self.cert_provider {
+            if self.client_ca.is_some() {
+                return Err(
+                    "the client ca is set by the config of the cert provider, not by with_client_ca"
+                        .into(),
+                );
+            }
+            tls = Some(provider);
+        } else if !self.certs.is_empty() && !self.keys.is_empty() {
             let mut keys = self.keys;
 
-            let config = rustls::ServerConfig::builder()
-                .with_safe_defaults()
-                .with_no_client_auth()
-                .with_single_cert(self.certs, keys.remove(0))
-                .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+            let config =
+                utils::tls::server_config(self.certs, keys.remove(0), self.client_ca.as_deref())?;
 
-            acceptor = Some(TlsAcceptor::from(Arc::new(config)));
+            tls = Some(CertProvider::fixed(Arc::new(config)));
+        } else if self.client_ca.is_some() {
+            return Err("client cert verification requires the tls of the server".into());
         } else {
-            acceptor = None;
+            tls = None;
         }
 
         let listener = match get_listener(name, addr).await {
@@ -199,26 +244,46 @@ impl DubboServer {
                     match res {
                         Ok(conn) => {
                             let (io, local_addr) = conn;
-                            let b :BoxIO;
-
-                            if !acceptor.is_none() {
-                                b = BoxIO::new(acceptor.as_ref().unwrap().clone().accept(io).await?);
-                            } else {
-                                b = io;
-                            }
-
-                            tracing::debug!("hyper serve, local address: {:?}", local_addr);
-                            let c = hyper::server::conn::Http::new()
-                                .http2_only(self.accept_http2)
+                            // the latest tls config, reloaded certs apply to the new connections
+                            let acceptor = tls.as_ref().map(|provider| TlsAcceptor::from(provider.config()));
+                            let mut http = hyper::server::conn::Http::new();
+                            http.http2_only(self.accept_http2)
                                 .http2_max_concurrent_streams(self.max_concurrent_streams)
                                 .http2_initial_connection_window_size(self.init_connection_window_size)
                                 .http2_initial_stream_window_size(self.init_stream_window_size)
                                 .http2_keep_alive_interval(self.http2_keepalive_interval)
                                 .http2_keep_alive_timeout(http2_keepalive_timeout)
-                                .http2_max_frame_size(self.max_frame_size)
-                                .serve_connection(b,svc.clone()).with_upgrades();
+                                .http2_max_frame_size(self.max_frame_size);
+                            let svc = svc.clone();
 
-                            tokio::spawn(c);
+                            // the handshake runs off the accept loop, a failed one only drops its connection
+                            tokio::spawn(async move {
+                                let (b, peer) = match acceptor {
+                                    Some(acceptor) => match acceptor.accept(io).await {
+                                        Ok(stream) => {
+                                            let peer = stream
+                                                .get_ref()
+                                                .1
+                                                .peer_certificates()
+                                                .map(|certs| PeerIdentity::new(certs.to_vec()));
+                                            (BoxIO::new(stream), peer)
+                                        }
+                                        Err(err) => {
+                                            tracing::warn!("tls handshake failed, local address: {:?}, err: {}", local_addr, err);
+                                            return;
+                                        }
+                                    },
+                                    None => (io, None),
+                                };
+
+                                tracing::debug!("hyper serve, local address: {:?}", local_addr);
+                                let c = http
+                                    .serve_connection(b, PeerService { inner: svc, peer })
+                                    .with_upgrades();
+                                if let Err(err) = c.await {
+                                    tracing::debug!("hyper serve, connection closed: {:?}", err);
+                                }
+                            });
                         },
                         Err(err) => tracing::error!("hyper serve, err: {:?}", err),
                     }
@@ -232,6 +297,54 @@ impl DubboServer {
     }
 }
 
+/// Shuts the health reporter down when the server stops.
+struct HealthGuard(Option<HealthReporter>);
+
+impl Drop for HealthGuard {
+    fn drop(&mut self) {
+        if let Some(health) = self.0.take() {
+            health.shutdown();
+        }
+    }
+}
+
+/// Serves the requests of a connection, the identity of its TLS peer is in the extensions
+/// of the requests and in the context of their handlers.
+#[derive(Clone)]
+struct PeerService<S> {
+    inner: S,
+    peer: Option<PeerIdentity>,
+}
+
+impl<S> Service<Request<Body>> for PeerService<S>
+where
+    S: Service<Request<Body>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+
+    type Error = S::Error;
+
+    type Future = crate::BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<Body>) -> Self::Future {
+        if let Some(peer) = self.peer.clone() {
+            req.extensions_mut().insert(peer);
+        }
+        Box::pin(RpcContext::peer_scope(
+            self.peer.clone(),
+            self.inner.call(req),
+        ))
+    }
+}
+
 // impl BusinessConfig for DubboServer {
 //     fn init() -> Self {
 //         let conf = config::get_global_config();
@@ -242,3 +355,33 @@ impl DubboServer {
 //         todo!()
 //     }
 // }
+
+#[cfg(test)]
+mod tests {
+    use crate::health::ServingStatus;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn not_serving_after_stop() {
+        let reporter = HealthReporter::new();
+        reporter.set_serving("");
+        let server = DubboServer::new()
+            .with_listener("tcp".to_string())
+            .with_health_reporter(reporter.clone());
+        let serving = tokio::spawn(server.serve("127.0.0.1:0".parse().unwrap()));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(
+            reporter.get_service_status(""),
+            Some(ServingStatus::Serving)
+        );
+
+        // the plain serve stops only when its future is dropped
+        serving.abort();
+        let _ = serving.await;
+        assert_eq!(
+            reporter.get_service_status(""),
+            Some(ServingStatus::NotServing)
+        );
+    }
+}
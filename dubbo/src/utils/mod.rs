@@ -18,3 +18,4 @@
 pub mod boxed;
 pub mod boxed_clone;
 pub mod tls;
+pub(crate) mod watch;
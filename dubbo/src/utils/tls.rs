@@ -15,22 +15,314 @@
  * limitations under the License.
  */
 
-use rustls_pemfile::{certs, rsa_private_keys};
+use arc_swap::ArcSwap;
+use dubbo_logger::tracing;
+use rustls_pemfile::{certs, read_all, Item};
 use std::{
+    fmt,
     fs::File,
     io::{self, BufReader},
-    path::Path,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
 };
-use tokio_rustls::rustls::{Certificate, PrivateKey};
+use tokio_rustls::rustls::{
+    server::AllowAnyAuthenticatedClient, Certificate, PrivateKey, RootCertStore, ServerConfig,
+};
+
+use crate::utils::watch::watch_files;
+
+/// How often the watched files of a [`CertProvider`] are checked for changes.
+pub const DEFAULT_WATCH_INTERVAL: Duration = Duration::from_secs(10);
 
+/// Loads the certs of a PEM file, a cert chain starts with the end entity cert.
 pub fn load_certs(path: &Path) -> io::Result<Vec<Certificate>> {
     certs(&mut BufReader::new(File::open(path)?))
         .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "invalid cert"))
         .map(|mut certs| certs.drain(..).map(Certificate).collect())
 }
 
+/// Loads the private keys of a PEM file, the RSA keys in PKCS#1, the EC keys in SEC1, and
+/// the RSA, ECDSA or Ed25519 keys in PKCS#8.
 pub fn load_keys(path: &Path) -> io::Result<Vec<PrivateKey>> {
-    rsa_private_keys(&mut BufReader::new(File::open(path)?))
+    read_all(&mut BufReader::new(File::open(path)?))
         .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "invalid key"))
-        .map(|mut keys| keys.drain(..).map(PrivateKey).collect())
+        .map(|items| {
+            items
+                .into_iter()
+                .filter_map(|item| match item {
+                    Item::RSAKey(key) | Item::PKCS8Key(key) | Item::ECKey(key) => {
+                        Some(PrivateKey(key))
+                    }
+                    _ => None,
+                })
+                .collect()
+        })
+}
+
+/// The store of the trusted ca certs, which verify the certs of the peers.
+pub fn root_store(ca_certs: &[Certificate]) -> Result<RootCertStore, crate::Error> {
+    if ca_certs.is_empty() {
+        return Err("no ca cert to verify the peer".into());
+    }
+    let mut roots = RootCertStore::empty();
+    for cert in ca_certs.iter() {
+        roots
+            .add(cert)
+            .map_err(|err| format!("invalid ca cert: {:?}", err))?;
+    }
+    Ok(roots)
+}
+
+/// The server config of the cert chain and key, which verifies the client certs against
+/// `client_ca` when given. It negotiates `h2` by ALPN, falling back to `http/1.1`.
+pub fn server_config(
+    certs: Vec<Certificate>,
+    key: PrivateKey,
+    client_ca: Option<&[Certificate]>,
+) -> Result<ServerConfig, crate::Error> {
+    let builder = ServerConfig::builder().with_safe_defaults();
+    let builder = match client_ca {
+        Some(client_ca) => builder
+            .with_client_cert_verifier(AllowAnyAuthenticatedClient::new(root_store(client_ca)?)),
+        None => builder.with_no_client_auth(),
+    };
+    let mut config = builder
+        .with_single_cert(certs, key)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(config)
+}
+
+/// The first private key of a PEM file.
+pub fn load_key(path: &Path) -> io::Result<PrivateKey> {
+    load_keys(path)?.into_iter().next().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("no private key in {:?}", path),
+        )
+    })
+}
+
+type BuildConfig<C> = dyn Fn() -> Result<C, crate::Error> + Send + Sync;
+
+/// Provides the rustls config of a server or client, built from cert files and rebuilt by
+/// [`reload`](CertProvider::reload) or when the files change once watched. New handshakes use
+/// the latest config, established connections keep theirs. A failed reload keeps the current
+/// config.
+pub struct CertProvider<C> {
+    files: Vec<PathBuf>,
+    build: Option<Box<BuildConfig<C>>>,
+    config: ArcSwap<C>,
+}
+
+impl<C> fmt::Debug for CertProvider<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CertProvider")
+            .field("files", &self.files)
+            .finish()
+    }
+}
+
+impl<C: Send + Sync + 'static> CertProvider<C> {
+    /// Builds the config by `build`, which reads `files`.
+    pub fn new<F>(files: Vec<PathBuf>, build: F) -> Result<Arc<Self>, crate::Error>
+    where
+        F: Fn() -> Result<C, crate::Error> + Send + Sync + 'static,
+    {
+        let config = build()?;
+        Ok(Arc::new(CertProvider {
+            files,
+            build: Some(Box::new(build)),
+            config: ArcSwap::from_pointee(config),
+        }))
+    }
+
+    /// Provides `config`, which is never reloaded.
+    pub fn fixed(config: Arc<C>) -> Arc<Self> {
+        Arc::new(CertProvider {
+            files: Vec::new(),
+            build: None,
+            config: ArcSwap::new(config),
+        })
+    }
+
+    pub fn config(&self) -> Arc<C> {
+        self.config.load_full()
+    }
+
+    /// Rebuilds the config from the files.
+    pub fn reload(&self) -> Result<(), crate::Error> {
+        let build = match self.build.as_ref() {
+            Some(v) => v,
+            None => return Ok(()),
+        };
+        match build() {
+            Ok(config) => {
+                self.config.store(Arc::new(config));
+                tracing::info!("tls config reloaded from {:?}", self.files);
+                Ok(())
+            }
+            Err(err) => {
+                tracing::error!(
+                    "reloading tls config from {:?} failed, keep the current: {}",
+                    self.files,
+                    err
+                );
+                Err(err)
+            }
+        }
+    }
+
+    /// Reloads the config every time one of the files is modified, until the provider is
+    /// dropped. Needs a tokio runtime.
+    pub fn watch(self: &Arc<Self>, interval: Duration) {
+        if self.build.is_none() || self.files.is_empty() {
+            return;
+        }
+        watch_files(self, self.files.clone(), interval, |provider: &Self| {
+            provider.reload()
+        });
+    }
+}
+
+impl CertProvider<ServerConfig> {
+    /// The server config of the PEM files of the cert chain and key, and of the ca certs which
+    /// verify the client certs.
+    pub fn server(
+        cert: PathBuf,
+        key: PathBuf,
+        client_ca: Option<PathBuf>,
+    ) -> Result<Arc<Self>, crate::Error> {
+        let mut files = vec![cert.clone(), key.clone()];
+        files.extend(client_ca.clone());
+        Self::new(files, move || {
+            let client_ca = match client_ca.as_ref() {
+                Some(path) => Some(load_certs(path)?),
+                None => None,
+            };
+            server_config(load_certs(&cert)?, load_key(&key)?, client_ca.as_deref())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::SystemTime,
+    };
+
+    use super::*;
+
+    #[test]
+    fn failed_reload_keeps_config() {
+        let builds = Arc::new(AtomicUsize::new(0));
+        let counter = builds.clone();
+        let provider = CertProvider::new(Vec::new(), move || {
+            match counter.fetch_add(1, Ordering::SeqCst) {
+                1 => Err("cert being written".into()),
+                n => Ok(n),
+            }
+        })
+        .unwrap();
+        assert_eq!(*provider.config(), 0);
+        assert!(provider.reload().is_err());
+        assert_eq!(*provider.config(), 0);
+        assert!(provider.reload().is_ok());
+        assert_eq!(*provider.config(), 2);
+    }
+
+    #[tokio::test]
+    async fn retry_failed_reload() {
+        let path = std::env::temp_dir().join(format!("dubbo-watch-{}.pem", std::process::id()));
+        std::fs::write(&path, "cert").unwrap();
+        let builds = Arc::new(AtomicUsize::new(0));
+        let counter = builds.clone();
+        let provider = CertProvider::new(vec![path.clone()], move || {
+            match counter.fetch_add(1, Ordering::SeqCst) {
+                1 => Err("cert being written".into()),
+                n => Ok(n),
+            }
+        })
+        .unwrap();
+        provider.watch(Duration::from_millis(20));
+        // the watch starts from the current modification time
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(10))
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let _ = std::fs::remove_file(&path);
+        // reloaded by the next tick without another modification
+        assert_eq!(*provider.config(), 2);
+        assert_eq!(builds.load(Ordering::SeqCst), 3);
+    }
+
+    fn testdata(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("testdata/tls")
+            .join(name)
+    }
+
+    #[test]
+    fn load_key_formats() {
+        // PKCS#8 and SEC1 of the same EC key, and an Ed25519 key in PKCS#8
+        for (cert, key) in [
+            ("server.pem", "server.key"),
+            ("server.pem", "server-ec.key"),
+            ("ed25519.pem", "ed25519.key"),
+        ] {
+            let key = load_key(&testdata(key)).unwrap();
+            let certs = load_certs(&testdata(cert)).unwrap();
+            assert!(server_config(certs, key, None).is_ok(), "{}", cert);
+        }
+        assert!(load_key(&testdata("server.pem")).is_err());
+    }
+
+    /// Handshakes with a server requiring client certs, by a client with `identity`.
+    async fn mtls_handshake(identity: Option<(&str, &str)>) -> io::Result<()> {
+        use crate::triple::transport::connector::tls_connector::ClientTlsConfig;
+        use tokio_rustls::{rustls::ServerName, TlsAcceptor, TlsConnector};
+
+        let ca = load_certs(&testdata("ca.pem")).unwrap();
+        let config = server_config(
+            load_certs(&testdata("server.pem")).unwrap(),
+            load_key(&testdata("server.key")).unwrap(),
+            Some(&ca),
+        )
+        .unwrap();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (io, _) = listener.accept().await.unwrap();
+            TlsAcceptor::from(Arc::new(config))
+                .accept(io)
+                .await
+                .map(|_| ())
+        });
+
+        let mut client = ClientTlsConfig::new().with_ca_certs(ca);
+        if let Some((cert, key)) = identity {
+            client = client.with_identity(
+                load_certs(&testdata(cert)).unwrap(),
+                load_key(&testdata(key)).unwrap(),
+            );
+        }
+        let connector = TlsConnector::from(Arc::new(client.client_config().unwrap()));
+        let io = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let name = ServerName::try_from("localhost").unwrap();
+        // the client may finish its side of a TLS 1.3 handshake before the server rejects it
+        let _ = connector.connect(name, io).await;
+        server.await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn reject_client_without_cert() {
+        assert!(mtls_handshake(None).await.is_err());
+        assert!(mtls_handshake(Some(("client.pem", "client.key")))
+            .await
+            .is_ok());
+    }
 }
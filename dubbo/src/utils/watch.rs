@@ -0,0 +1,75 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::{
+    path::PathBuf,
+    sync::{Arc, Weak},
+    time::{Duration, SystemTime},
+};
+
+use dubbo_logger::tracing;
+
+/// Calls `reload` on `target` every time one of `files` is modified, checking the modification
+/// times every `interval`, until `target` is dropped. A failed reload is tried again on the
+/// next check, eg. for a file written in several steps, `reload` logs its own errors.
+pub(crate) fn watch_files<T, E, F>(
+    target: &Arc<T>,
+    files: Vec<PathBuf>,
+    interval: Duration,
+    reload: F,
+) where
+    T: Send + Sync + 'static,
+    E: 'static,
+    F: Fn(&T) -> Result<(), E> + Send + 'static,
+{
+    let runtime = match tokio::runtime::Handle::try_current() {
+        Ok(v) => v,
+        Err(_) => {
+            tracing::warn!("watching {:?} needs a tokio runtime, not reloaded", files);
+            return;
+        }
+    };
+    runtime.spawn(watch_loop(Arc::downgrade(target), files, interval, reload));
+}
+
+async fn watch_loop<T, E, F>(target: Weak<T>, files: Vec<PathBuf>, interval: Duration, reload: F)
+where
+    F: Fn(&T) -> Result<(), E>,
+{
+    let modified = || -> Vec<Option<SystemTime>> {
+        files
+            .iter()
+            .map(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+            .collect()
+    };
+    let mut last = modified();
+    loop {
+        tokio::time::sleep(interval).await;
+        let target = match target.upgrade() {
+            Some(v) => v,
+            None => return,
+        };
+        let current = modified();
+        // a file being replaced is missing for a moment
+        if current.iter().any(Option::is_none) || current == last {
+            continue;
+        }
+        if reload(&target).is_ok() {
+            last = current;
+        }
+    }
+}
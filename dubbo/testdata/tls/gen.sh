@@ -0,0 +1,50 @@
+#!/bin/sh
+#
+# Licensed to the Apache Software Foundation (ASF) under one or more
+# contributor license agreements.  See the NOTICE file distributed with
+# this work for additional information regarding copyright ownership.
+# The ASF licenses this file to You under the Apache License, Version 2.0
+# (the "License"); you may not use this file except in compliance with
+# the License.  You may obtain a copy of the License at
+#
+#     http://www.apache.org/licenses/LICENSE-2.0
+#
+# Unless required by applicable law or agreed to in writing, software
+# distributed under the License is distributed on an "AS IS" BASIS,
+# WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+# See the License for the specific language governing permissions and
+# limitations under the License.
+
+# Generates the certs and keys of the TLS tests, valid for 100 years:
+#   ca.pem, ca.key                 the ca signing the other certs
+#   server.pem, server.key         EC P-256 server cert of localhost, the key in PKCS#8
+#   server-ec.key                  the same key in SEC1
+#   ed25519.pem, ed25519.key       Ed25519 server cert of localhost, the key in PKCS#8
+#   client.pem, client.key         EC P-256 client cert
+set -e
+cd "$(dirname "$0")"
+
+DAYS=36500
+openssl req -x509 -new -nodes -newkey ec -pkeyopt ec_paramgen_curve:P-256 -days $DAYS \
+    -subj "/CN=dubbo test ca" -keyout ca.key -out ca.pem \
+    -addext "basicConstraints=critical,CA:TRUE" -addext "keyUsage=critical,keyCertSign,cRLSign"
+
+# sign NAME SUBJECT EXTENSIONS, the key NAME.key must exist
+sign() {
+    openssl req -new -key "$1.key" -subj "$2" -out "$1.csr"
+    printf "%s" "$3" > "$1.ext"
+    openssl x509 -req -in "$1.csr" -CA ca.pem -CAkey ca.key -CAcreateserial -days $DAYS \
+        -extfile "$1.ext" -out "$1.pem"
+    rm -f "$1.csr" "$1.ext" ca.srl
+}
+
+SERVER_EXT="subjectAltName=DNS:localhost
+extendedKeyUsage=serverAuth"
+openssl genpkey -algorithm EC -pkeyopt ec_paramgen_curve:P-256 -out server.key
+openssl pkey -in server.key -out server-ec.key -traditional
+sign server "/CN=localhost" "$SERVER_EXT"
+openssl genpkey -algorithm ED25519 -out ed25519.key
+sign ed25519 "/CN=localhost" "$SERVER_EXT"
+
+openssl genpkey -algorithm EC -pkeyopt ec_paramgen_curve:P-256 -out client.key
+sign client "/CN=dubbo test client" "extendedKeyUsage=clientAuth"
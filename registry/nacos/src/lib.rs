@@ -23,7 +23,9 @@ use std::{
 };
 
 use anyhow::anyhow;
-use dubbo::registry::{NotifyListener, Registry, RegistryNotifyListener, ServiceEvent};
+use dubbo::registry::{
+    NotifyListener, Registry, RegistryNotifyListener, ServiceEvent, CHANGE_ACTION,
+};
 use dubbo_logger::tracing::{error, info, warn};
 use nacos_sdk::api::naming::{NamingService, NamingServiceBuilder, ServiceInstance};
 
@@ -526,7 +528,7 @@ impl nacos_sdk::api::naming::NamingEventListener for NotifyListenerWrapper {
         }
         let notify_event = ServiceEvent {
             key: service_name,
-            action: String::from("CHANGE"),
+            action: CHANGE_ACTION.to_string(),
             service: urls,
         };
         self.0.notify(notify_event);
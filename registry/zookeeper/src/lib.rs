@@ -38,7 +38,7 @@ use dubbo::{
     codegen::BoxRegistry,
     registry::{
         integration::ClusterRegistryIntegration, memory_registry::MemoryRegistry, NotifyListener,
-        Registry, RegistryNotifyListener, ServiceEvent,
+        Registry, RegistryNotifyListener, ServiceEvent, CHANGE_ACTION,
     },
     StdError,
 };
@@ -156,7 +156,7 @@ impl ZookeeperRegistry {
             Ok(_) => Ok(()),
             Err(err) => {
                 error!("zk path {} parent not exists.", path);
-                Err(Box::try_from(err).unwrap())
+                Err(Box::from(err))
             }
         }
     }
@@ -205,11 +205,9 @@ impl ZookeeperRegistry {
 
     pub fn get_data(&self, path: &str, watch: bool) -> Option<String> {
         if self.exists_path(path) {
-            let zk_result = self.zk_client.get_data(path, watch);
-            if let Ok(..) = zk_result {
-                Some(String::from_utf8(zk_result.unwrap().0).unwrap())
-            } else {
-                None
+            match self.zk_client.get_data(path, watch) {
+                Ok((data, _)) => Some(String::from_utf8(data).unwrap()),
+                Err(_) => None,
             }
         } else {
             None
@@ -307,7 +305,7 @@ impl Registry for ZookeeperRegistry {
         info!("notifying {}->{:?}", service_name, result);
         listener.notify(ServiceEvent {
             key: service_name,
-            action: String::from("ADD"),
+            action: CHANGE_ACTION.to_string(),
             service: result,
         });
         Ok(())
@@ -354,7 +352,7 @@ impl Watcher for ServiceInstancesChangedListener {
             info!("notify {}->{:?}", self.service_name, result);
             self.listener.notify(ServiceEvent {
                 key: self.service_name.clone(),
-                action: String::from("ADD"),
+                action: CHANGE_ACTION.to_string(),
                 service: result,
             });
         }
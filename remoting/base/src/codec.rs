@@ -59,7 +59,7 @@ impl Default for CodecRegistry {
 impl CodecRegistry {
     pub fn get_codec(&self, protocol: ProtocolName) -> Option<BoxedCodec> {
         let registry_map = &self.registry;
-        if let true = registry_map.contains_key(protocol) {
+        if registry_map.contains_key(protocol) {
             let option = registry_map.get(protocol);
             let codec = option.as_deref().unwrap();
             Some(codec.clone())
@@ -72,7 +72,7 @@ impl CodecRegistry {
         protocol: ProtocolName,
         codec: BoxedCodec,
     ) -> anyhow::Result<(), CodecError> {
-        if let true = self.registry.contains_key(protocol) {
+        if self.registry.contains_key(protocol) {
             return Err(CodecError::RegistryExistsProtocol(protocol));
         } else {
             self.registry.insert(protocol, codec);
@@ -117,7 +117,7 @@ mod tests {
     fn test_registry() {
         let mut codec_registry = CodecRegistry::default();
         codec_registry
-            .set_codec("test", BoxedCodec(Arc::new(TestCodec::default())))
+            .set_codec("test", BoxedCodec(Arc::new(TestCodec)))
             .unwrap();
         assert!(codec_registry.is_registered("test"));
     }
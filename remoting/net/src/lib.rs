@@ -84,12 +84,7 @@ impl TryFrom<tokio::net::unix::SocketAddr> for Address {
         Ok(Address::Unix(Cow::Owned(
             value
                 .as_pathname()
-                .ok_or_else(|| {
-                    std::io::Error::new(
-                        std::io::ErrorKind::Other,
-                        "unix socket doesn't have an address",
-                    )
-                })?
+                .ok_or_else(|| std::io::Error::other("unix socket doesn't have an address"))?
                 .to_owned(),
         )))
     }